@@ -0,0 +1,62 @@
+//! Timing the read path of each backend, for `raise bench`
+
+use crate::{hyprctl, Client, Result};
+use std::time::{Duration, Instant};
+
+/// A way of listing clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Spawning `hyprctl clients -j`
+    Hyprctl,
+    /// Writing `j/clients` to the control socket
+    Socket,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 2] = [Backend::Hyprctl, Backend::Socket];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Hyprctl => "hyprctl",
+            Backend::Socket => "socket",
+        }
+    }
+
+    pub fn clients(self) -> Result<Vec<Client>> {
+        match self {
+            Backend::Hyprctl => hyprctl::clients(),
+            Backend::Socket => hyprctl::query_socket("clients"),
+        }
+    }
+}
+
+/// The spread of the time one query took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+impl Summary {
+    /// Summarize timings, where there is nothing to summarize without any
+    pub fn of(mut timings: Vec<Duration>) -> Option<Self> {
+        timings.sort();
+        Some(Summary {
+            min: *timings.first()?,
+            median: timings[timings.len() / 2],
+            max: *timings.last()?,
+        })
+    }
+}
+
+/// List clients through the backend this many times, stopping at the first failure
+pub fn run(backend: Backend, iterations: usize) -> Result<Option<Summary>> {
+    let mut timings = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        backend.clients()?;
+        timings.push(start.elapsed());
+    }
+    Ok(Summary::of(timings))
+}
@@ -0,0 +1,57 @@
+use crate::Client;
+use miniserde::{json, Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parsed clients together with the time they were queried
+#[derive(Serialize, Deserialize)]
+struct Cached {
+    timestamp: u64,
+    clients: Vec<Client>,
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Directory for files that only live as long as the session
+pub fn runtime_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Each instance has a cache of its own, clients of one are no use in another
+fn cache_path(dir: &Path, signature: &str) -> PathBuf {
+    dir.join(format!("raise-clients-{signature}.json"))
+}
+
+/// Read the clients cached in `dir` for the instance, if they are younger than `ttl`
+/// milliseconds
+pub fn load(dir: &Path, signature: &str, ttl: u64) -> Option<Vec<Client>> {
+    let contents = std::fs::read_to_string(cache_path(dir, signature)).ok()?;
+    let cached = json::from_str::<Cached>(&contents).ok()?;
+    if now_millis().saturating_sub(cached.timestamp) <= ttl {
+        Some(cached.clients)
+    } else {
+        None
+    }
+}
+
+/// Write clients to the cache, ignoring failures since the cache is only an optimization
+pub fn store(dir: &Path, signature: &str, clients: &[Client]) {
+    let cached = Cached {
+        timestamp: now_millis(),
+        clients: clients.to_vec(),
+    };
+    let _ = std::fs::write(cache_path(dir, signature), json::to_string(&cached));
+}
+
+/// Drop the cache of the instance, called before every dispatch since it changes window
+/// state
+pub fn invalidate(dir: &Path, signature: &str) {
+    let _ = std::fs::remove_file(cache_path(dir, signature));
+}
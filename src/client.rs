@@ -0,0 +1,141 @@
+use miniserde::de::Visitor;
+use miniserde::ser::Fragment;
+use miniserde::{make_place, Deserialize, Serialize};
+
+/// A window as reported by `hyprctl clients -j`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Client {
+    pub class: String,
+    /// The class the window was created with, which some applications, like Electron
+    /// ones, change once loaded
+    #[serde(rename = "initialClass")]
+    pub initial_class: Option<String>,
+    pub title: String,
+    pub address: String,
+    pub pid: i64,
+    pub workspace: Workspace,
+    pub monitor: i64,
+    pub pinned: bool,
+    /// Whether the window is an X11 one, running through XWayland
+    pub xwayland: bool,
+    /// Whether the window floats, instead of being tiled
+    pub floating: bool,
+    /// Whether and how the window is fullscreen. Not reported by every Hyprland version.
+    pub fullscreen: Option<Fullscreen>,
+    /// Top left corner in global layout coordinates, as `[x, y]`
+    pub at: Option<Vec<i64>>,
+    /// Width and height, as `[width, height]`
+    pub size: Option<Vec<i64>>,
+    /// Address of the window this one swallowed, `0x0` for none. Not reported by every
+    /// Hyprland version, in which case nothing is known to be swallowed.
+    pub swallowing: Option<String>,
+    /// Addresses of the members of the group this window is in, empty when not grouped
+    pub grouped: Option<Vec<String>>,
+    /// Whether the window asks for attention. Hyprland does not report this, raise fills it
+    /// in from what `raise watch --track-urgent` noted down.
+    pub urgent: Option<bool>,
+    /// How recently the window was focused, 0 being the focused one. Not reported by
+    /// every Hyprland version.
+    #[serde(rename = "focusHistoryID")]
+    pub focus_history_id: Option<i64>,
+}
+
+impl Client {
+    /// Addresses of windows hidden because another window swallowed them
+    pub fn swallowed(clients: &[Client]) -> Vec<&str> {
+        clients
+            .iter()
+            .filter_map(|client| client.swallowing.as_deref())
+            .filter(|address| *address != "0x0")
+            .collect()
+    }
+
+    /// The most recently created of the clients, in the order `hyprctl clients` listed
+    /// them. Hyprland reports no creation time, but lists windows as they were created, so
+    /// the last one listed is the newest.
+    pub fn newest<'a>(clients: &[&'a Client]) -> Option<&'a Client> {
+        clients.last().copied()
+    }
+
+    /// The middle of the window, when its geometry is known
+    pub fn center(&self) -> Option<(i64, i64)> {
+        match (self.at.as_deref(), self.size.as_deref()) {
+            (Some(&[x, y]), Some(&[width, height])) => Some((x + width / 2, y + height / 2)),
+            _ => None,
+        }
+    }
+
+    /// Whether the window is on a special workspace, like a scratchpad
+    pub fn is_special(&self) -> bool {
+        self.workspace.name.starts_with("special:")
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen.is_some_and(|fullscreen| fullscreen.0 != 0)
+    }
+
+    pub fn is_urgent(&self) -> bool {
+        self.urgent == Some(true)
+    }
+
+    /// Whether there is an address to dispatch to, which a window being torn down can be
+    /// listed without
+    pub fn has_address(&self) -> bool {
+        self.address.len() > 2 && self.address.starts_with("0x")
+    }
+
+    pub fn is_grouped(&self) -> bool {
+        self.grouped
+            .as_ref()
+            .is_some_and(|members| !members.is_empty())
+    }
+}
+
+/// The workspace a client is on
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+}
+
+/// The fullscreen state of a window, 0 for none, 1 for maximized and 2 or 3 for
+/// fullscreen. Hyprland before v0.42 reports a boolean instead, read as 0 or 2.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Fullscreen(pub u64);
+
+impl Fullscreen {
+    /// The mode of the `fullscreen` dispatcher that toggles this state
+    pub fn mode(self) -> &'static str {
+        if self.0 == 1 {
+            "1"
+        } else {
+            "0"
+        }
+    }
+}
+
+impl Deserialize for Fullscreen {
+    fn begin(out: &mut Option<Self>) -> &mut dyn Visitor {
+        make_place!(Place);
+
+        impl Visitor for Place<Fullscreen> {
+            fn boolean(&mut self, fullscreen: bool) -> miniserde::Result<()> {
+                self.out = Some(Fullscreen(if fullscreen { 2 } else { 0 }));
+                Ok(())
+            }
+
+            fn nonnegative(&mut self, state: u64) -> miniserde::Result<()> {
+                self.out = Some(Fullscreen(state));
+                Ok(())
+            }
+        }
+
+        Place::new(out)
+    }
+}
+
+impl Serialize for Fullscreen {
+    fn begin(&self) -> Fragment<'_> {
+        Fragment::U64(self.0)
+    }
+}
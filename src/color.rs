@@ -0,0 +1,70 @@
+use miniserde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// When output meant for people gets colored
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only on a terminal, and only without `NO_COLOR`
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "always")]
+    Always,
+    #[serde(rename = "never")]
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(choice: &str) -> Result<Self, Self::Err> {
+        match choice {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "unknown color choice `{choice}`, expected `auto`, `always` or `never`"
+            )),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Whether stderr, where all human-facing output goes, should be colored
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            // https://no-color.org asks to ignore an empty NO_COLOR
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+}
+
+impl Color {
+    /// The text wrapped in this color's escape codes, or as is when `enabled` is false
+    pub fn paint(self, text: impl Display, enabled: bool) -> String {
+        let code = match self {
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+        };
+        if enabled {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use crate::color::ColorChoice;
+use crate::hyprctl::FocusMethod;
+use crate::keys::KeysTool;
+use crate::launch::{LaunchCommand, Launcher};
+use crate::order::Order;
+use crate::policy::{OnMany, OnNone, OnOne};
+use crate::{RaiseError, Result};
+use miniserde::{json, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// A value that can also be given through a `RAISE_*` environment variable
+pub trait Setting: Sized {
+    fn parse(value: &str) -> Result<Self, String>;
+}
+
+impl Setting for String {
+    fn parse(value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+}
+
+impl Setting for bool {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "1" | "true" | "yes" => Ok(true),
+            "0" | "false" | "no" | "" => Ok(false),
+            _ => Err(format!("expected a boolean, got `{value}`")),
+        }
+    }
+}
+
+impl Setting for u64 {
+    fn parse(value: &str) -> Result<Self, String> {
+        value
+            .parse()
+            .map_err(|_| format!("expected a number, got `{value}`"))
+    }
+}
+
+impl Setting for i64 {
+    fn parse(value: &str) -> Result<Self, String> {
+        value
+            .parse()
+            .map_err(|_| format!("expected a number, got `{value}`"))
+    }
+}
+
+impl Setting for usize {
+    fn parse(value: &str) -> Result<Self, String> {
+        value
+            .parse()
+            .map_err(|_| format!("expected a number, got `{value}`"))
+    }
+}
+
+impl Setting for FocusMethod {
+    fn parse(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl Setting for ColorChoice {
+    fn parse(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl Setting for Launcher {
+    fn parse(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+/// Only the shell command form, lists need the configuration file
+impl Setting for LaunchCommand {
+    fn parse(value: &str) -> Result<Self, String> {
+        Ok(LaunchCommand::Shell(value.to_string()))
+    }
+}
+
+impl Setting for Order {
+    fn parse(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl Setting for KeysTool {
+    fn parse(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl Setting for OnNone {
+    fn parse(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl Setting for OnOne {
+    fn parse(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl Setting for OnMany {
+    fn parse(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+/// Lists are separated by commas in the environment
+impl Setting for Vec<String> {
+    fn parse(value: &str) -> Result<Self, String> {
+        Ok(value
+            .split(',')
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect())
+    }
+}
+
+impl<T: Setting> Setting for Option<T> {
+    fn parse(value: &str) -> Result<Self, String> {
+        T::parse(value).map(Some)
+    }
+}
+
+/// Read `RAISE_<NAME>` for a setting, if set
+fn env_setting<T: Setting>(name: &str) -> Result<Option<T>> {
+    let variable = format!("RAISE_{}", name.to_uppercase());
+    match std::env::var(&variable) {
+        Ok(value) => T::parse(&value)
+            .map(Some)
+            .map_err(|reason| RaiseError::Config(format!("${variable}: {reason}"))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Declares every setting once, as a field of both the effective configuration and of
+/// the layers it is merged from
+macro_rules! settings {
+    ($($(#[doc = $doc:literal])* $name:ident: $ty:ty,)*) => {
+        /// Effective settings, after overlaying profile, environment and command line
+        #[derive(Serialize, Debug, Default, Clone)]
+        pub struct RaiseConfig {
+            $($(#[doc = $doc])* pub $name: $ty,)*
+        }
+
+        /// One source of settings, where unset values fall through to the layer below
+        #[derive(Deserialize, Debug, Default, Clone)]
+        pub struct Layer {
+            $(pub $name: Option<$ty>,)*
+        }
+
+        impl RaiseConfig {
+            fn overlay(&mut self, layer: Layer) {
+                $(if let Some(value) = layer.$name {
+                    self.$name = value;
+                })*
+            }
+        }
+
+        impl Layer {
+            /// Settings given as `RAISE_<NAME>`, like `RAISE_MOVE_TO_CURRENT=1`
+            pub fn from_env() -> Result<Self> {
+                Ok(Self {
+                    $($name: env_setting(stringify!($name))?,)*
+                })
+            }
+        }
+    };
+}
+
+settings! {
+    /// Class to focus
+    class: Option<String>,
+    /// Compare classes after Unicode normalization
+    normalize: bool,
+    /// Also match the class windows were created with
+    class_or_initial: bool,
+    /// `reported=canonical` aliases of classes, on top of the configuration file's
+    map_class: Vec<String>,
+    /// Match windows without a class
+    classless: bool,
+    /// Match the class of the focused window
+    current_class: bool,
+    /// Title substring to focus
+    title: Option<String>,
+    /// Only match XWayland windows, or only native ones
+    xwayland: Option<bool>,
+    /// Only match floating windows, or only tiled ones
+    floating: Option<bool>,
+    /// `field=value` conditions on the JSON of matching windows
+    match_json: Vec<String>,
+    /// Command to launch, as a shell command line or as a program and its arguments
+    launch: Option<LaunchCommand>,
+    /// Keep directly spawned programs independent of raise
+    detach: bool,
+    /// What starts the command to launch
+    launcher: Launcher,
+    /// File the output of spawned programs is appended to
+    log_launch: Option<String>,
+    /// Directory to launch in
+    cwd: Option<String>,
+    /// Glob the workspace name of matching windows must match
+    workspace_pattern: Option<String>,
+    /// Only match windows on special workspaces, and bring them to the current one
+    only_special: bool,
+    /// Skip windows hidden by swallowing
+    skip_swallowed: bool,
+    /// Milliseconds clients may be reused from a previous invocation
+    cache: Option<u64>,
+    /// Milliseconds after acting on a class during which raise does nothing for it, 0 to
+    /// always act
+    debounce: u64,
+    /// Directory for state kept between invocations
+    state_dir: Option<String>,
+    /// Special workspace to toggle
+    toggle_special: Option<String>,
+    /// Special workspace to launch into without showing it
+    launch_hidden: Option<String>,
+    /// Move the window to the current workspace before focusing it
+    move_to_current: bool,
+    /// Move every matching window to the current workspace
+    gather: bool,
+    /// Give focus back to the window focused before gathering
+    keep_focus: bool,
+    /// Move the window this many workspaces over before focusing it
+    move_relative: Option<i64>,
+    /// Move the window to the nearest empty workspace before focusing it
+    move_to_nearest_empty: bool,
+    /// Only move launched windows, leaving existing ones where they are
+    on_launch_only_move: bool,
+    /// Only consider empty workspaces on the current monitor
+    empty_on_monitor: bool,
+    /// Make a moved fullscreen window fullscreen again
+    restore_fullscreen: bool,
+    /// Move without shifting focus
+    silent_move: bool,
+    /// Dispatcher used for moving windows
+    move_dispatcher: Option<String>,
+    /// Milliseconds to wait for a launched window
+    wait: Option<u64>,
+    /// Focus the launched window once it appears
+    after_launch_focus: bool,
+    /// Class of the launched window to wait for and focus, when it differs from `class`
+    wait_for: Option<String>,
+    /// Fail when no matching window appears after launching
+    require_launch: bool,
+    /// Launch, then only return once the new window is focused
+    launch_and_wait_for_focus: bool,
+    /// Bring every matching window to the front
+    raise_all: bool,
+    /// Send dispatches one by one
+    no_batch: bool,
+    /// Carry on past dispatches hyprctl rejects
+    best_effort: bool,
+    /// How to focus windows
+    focus_method: FocusMethod,
+    /// Move the cursor to the middle of the focused window
+    cursor_center: bool,
+    /// Float and center the window, putting it back as it was on the next peek
+    peek: bool,
+    /// Milliseconds after which a peeked window is put back by itself
+    peek_timeout: Option<u64>,
+    /// Check that focus landed on the window, failing otherwise
+    verify_focus: bool,
+    /// Focus the monitor of the window first
+    focus_monitor: bool,
+    /// Focus the nth matching window, starting at 1
+    index: Option<usize>,
+    /// Order matching windows are cycled through
+    order: Order,
+    /// Seed for shuffling, for the same order every time
+    seed: Option<u64>,
+    /// Focus the most recently created matching window
+    newest: bool,
+    /// Pick among several matching windows with a menu
+    select_menu: bool,
+    /// Menu program to pick with, reading entries on stdin
+    menu: Option<String>,
+    /// Go back to the previous window when a matching one is focused
+    toggle_last: bool,
+    /// Minimize a focused matching window, restoring it where it was when raised again
+    raise_or_minimize: bool,
+    /// Focus a matching window that asks for attention before cycling
+    urgent_first: bool,
+    /// Never launch
+    no_launch: bool,
+    /// Launch even when a matching window exists
+    force_launch: bool,
+    /// What to do when no window matches
+    on_none: Option<OnNone>,
+    /// What to do when one window matches
+    on_one: Option<OnOne>,
+    /// What to do when several windows match
+    on_many: Option<OnMany>,
+    /// Keys to send to the window once it is focused
+    send_keys: Option<String>,
+    /// What sends the keys
+    keys_tool: KeysTool,
+    /// Submap to switch to once the window is focused
+    enter_submap: Option<String>,
+    /// Return to the default submap once the window is focused
+    reset_submap: bool,
+    /// Launch into the group of a matching window
+    join_group: bool,
+    /// Close the matching window instead of focusing it
+    close: bool,
+    /// Milliseconds a closed window gets before its process is killed
+    grace_close: Option<u64>,
+    /// Match windows of every running instance, not only the one in the environment
+    all_instances: bool,
+    /// Launch even without a Hyprland instance
+    launch_on_no_compositor: bool,
+    /// Fail instead of launching when the windows cannot be listed
+    strict: bool,
+    /// Print the time spent in each phase
+    timings: bool,
+    /// Print diagnostics
+    verbose: bool,
+    /// When to color diagnostics
+    color: ColorChoice,
+}
+
+/// Contents of the configuration file
+#[derive(Deserialize, Debug, Default)]
+pub struct ConfigFile {
+    pub profiles: Option<BTreeMap<String, Layer>>,
+    /// Canonical class by reported class, for every profile
+    pub class_map: Option<BTreeMap<String, String>>,
+}
+
+impl ConfigFile {
+    /// `$RAISE_CONFIG`, otherwise `raise/config.json` in the XDG config directory
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("RAISE_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("raise").join("config.json"))
+    }
+
+    /// Read the configuration file, where a missing file is the same as an empty one
+    pub fn load(path: Option<PathBuf>) -> Result<Self> {
+        let Some(path) = path.or_else(Self::default_path) else {
+            return Ok(<Self as Default>::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => json::from_str(&contents).map_err(|_| {
+                RaiseError::Config(format!("{} is not a valid configuration", path.display()))
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                Ok(<Self as Default>::default())
+            }
+            Err(error) => Err(RaiseError::Config(format!("{}: {error}", path.display()))),
+        }
+    }
+
+    fn profile(&self, name: &str) -> Result<Layer> {
+        self.profiles
+            .as_ref()
+            .and_then(|profiles| profiles.get(name))
+            .cloned()
+            .ok_or_else(|| RaiseError::Config(format!("no profile named `{name}`")))
+    }
+}
+
+impl RaiseConfig {
+    /// The single place settings are merged: the profile, overlaid with the environment,
+    /// overlaid with the command line
+    pub fn merge(file: &ConfigFile, profile: Option<&str>, cli: Layer) -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(name) = profile {
+            config.overlay(file.profile(name)?);
+        }
+        config.overlay(Layer::from_env()?);
+        config.overlay(cli);
+        Ok(config)
+    }
+}
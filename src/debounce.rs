@@ -0,0 +1,23 @@
+use crate::cache::now_millis;
+use miniserde::json;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Whether the last action on what `key` selects was less than `window` milliseconds ago, recording
+/// this one as the last otherwise, in the state file in `dir`. Without a readable state
+/// file nothing happened yet.
+pub fn bounced(dir: &Path, key: &str, window: u64) -> bool {
+    let path = dir.join("raise-debounce.json");
+    let contents = std::fs::read_to_string(&path).unwrap_or_default();
+    let mut last = json::from_str::<BTreeMap<String, u64>>(&contents).unwrap_or_default();
+    let now = now_millis();
+    if last
+        .get(key)
+        .is_some_and(|at| now.saturating_sub(*at) < window)
+    {
+        return true;
+    }
+    last.insert(key.to_string(), now);
+    let _ = std::fs::write(path, json::to_string(&last));
+    false
+}
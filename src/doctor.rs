@@ -0,0 +1,59 @@
+use crate::launch::find_in_path;
+use crate::version::{self, Version};
+use crate::{hyprctl, Client};
+
+/// The outcome of one environment check, with what was found or what went wrong
+pub struct Check {
+    pub name: &'static str,
+    pub result: Result<String, String>,
+}
+
+fn check(name: &'static str, result: Result<String, String>) -> Check {
+    Check { name, result }
+}
+
+/// Everything raise depends on, in the order it would fail at
+pub fn checks() -> Vec<Check> {
+    let binary = find_in_path("hyprctl")
+        .map(|path| path.display().to_string())
+        .ok_or_else(|| "not found in PATH".to_string());
+    let instance = match std::env::var("HYPRLAND_INSTANCE_SIGNATURE") {
+        Ok(signature) => Ok(format!("$HYPRLAND_INSTANCE_SIGNATURE is {signature}")),
+        Err(_) => hyprctl::check_instance()
+            .map(|()| "found a running instance".into())
+            .map_err(|error| error.to_string()),
+    };
+    let socket = hyprctl::socket_path()
+        .map(|path| path.display().to_string())
+        .ok_or_else(|| "no .socket.sock for this instance".to_string());
+    // Query directly, the cached version would not show whether hyprctl responds
+    let version = hyprctl::query::<Version>("version");
+    let responds = match &version {
+        Ok(version) => Ok(format!(
+            "Hyprland {} (commit {})",
+            version.tag, version.commit
+        )),
+        Err(error) => Err(error.to_string()),
+    };
+    let clients = hyprctl::query::<Vec<Client>>("clients")
+        .map(|clients| format!("{} windows", clients.len()))
+        .map_err(|error| error.to_string());
+    let (major, minor, patch) = version::MINIMUM;
+    let supported = match &version {
+        Ok(version) if version.is_supported() => Ok(format!("{} is supported", version.tag)),
+        Ok(version) => Err(format!(
+            "{} is older than v{major}.{minor}.{patch}",
+            version.tag
+        )),
+        Err(_) => Err("unknown, hyprctl did not respond".into()),
+    };
+
+    vec![
+        check("hyprctl", binary),
+        check("instance", instance),
+        check("socket", socket),
+        check("responds", responds),
+        check("clients", clients),
+        check("version", supported),
+    ]
+}
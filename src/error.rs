@@ -0,0 +1,58 @@
+use std::process::ExitStatus;
+
+/// Failure modes of the library, so callers can match instead of parsing messages
+#[derive(Debug, thiserror::Error)]
+pub enum RaiseError {
+    #[error("`hyprctl` was not found in PATH")]
+    HyprctlNotFound,
+
+    #[error("No Hyprland instance found, is $HYPRLAND_INSTANCE_SIGNATURE set?")]
+    NoCompositor,
+
+    #[error("Running `hyprctl` failed")]
+    Io(#[from] std::io::Error),
+
+    #[error("`hyprctl` exited with {status}: {stderr}")]
+    HyprctlFailed { status: ExitStatus, stderr: String },
+
+    #[error("`hyprctl {command}` failed: {reply}")]
+    DispatchFailed { command: String, reply: String },
+
+    #[error("`wtype` could not send {keys}, it exited with {status}")]
+    KeysFailed { keys: String, status: ExitStatus },
+
+    #[error("Failed to parse `{0}`")]
+    ParseFailed(String),
+
+    #[error("Failed to launch `{command}`")]
+    LaunchFailed {
+        command: String,
+        source: std::io::Error,
+    },
+
+    #[error("Failed to run the menu `{command}`")]
+    MenuFailed {
+        command: String,
+        source: std::io::Error,
+    },
+
+    #[error("`{command}` opened no matching window within {timeout}ms")]
+    LaunchTimedOut { command: String, timeout: u64 },
+
+    #[error("{address} did not get focused within {timeout}ms")]
+    FocusTimedOut { address: String, timeout: u64 },
+
+    #[error("Focusing {address} left {active} focused")]
+    FocusIntercepted { address: String, active: String },
+
+    #[error("No window matches")]
+    NoMatch,
+
+    #[error("Invalid arguments: {0}")]
+    InvalidArgs(String),
+
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+}
+
+pub type Result<T, E = RaiseError> = std::result::Result<T, E>;
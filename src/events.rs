@@ -0,0 +1,113 @@
+//! Hyprland's event socket, which `raise watch` listens on
+
+use crate::Result;
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An event announced on the event socket, of those `raise watch` acts on or keeps track
+/// of
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// `openwindow>>ADDRESS,WORKSPACE,CLASS,TITLE`
+    OpenWindow { address: String, class: String },
+    /// `urgent>>ADDRESS`, which leaves looking up the class to the caller
+    Urgent { address: String },
+    /// `activewindow>>CLASS,TITLE`, which names no address
+    ActiveWindow { class: String },
+    /// `activewindowv2>>ADDRESS`, following `activewindow`, where no address is no window
+    ActiveWindowV2 { address: Option<String> },
+    /// `closewindow>>ADDRESS`
+    CloseWindow { address: String },
+    /// `configreloaded>>`
+    ConfigReloaded,
+}
+
+impl Event {
+    /// Read an event from its line, where events of other kinds are `None`. Hyprland
+    /// writes addresses without `0x`, which is added back.
+    pub fn parse(line: &str) -> Option<Event> {
+        let (name, data) = line.split_once(">>")?;
+        let address = |address: &str| format!("0x{address}");
+        match name {
+            "openwindow" => {
+                // The title is last, since it may contain commas itself
+                let mut fields = data.splitn(4, ',');
+                let (window, _workspace, class) = (fields.next()?, fields.next()?, fields.next()?);
+                Some(Event::OpenWindow {
+                    address: address(window),
+                    class: class.into(),
+                })
+            }
+            "urgent" => Some(Event::Urgent {
+                address: address(data),
+            }),
+            "activewindow" => {
+                let (class, _title) = data.split_once(',')?;
+                Some(Event::ActiveWindow {
+                    class: class.into(),
+                })
+            }
+            "activewindowv2" => Some(Event::ActiveWindowV2 {
+                address: Some(data)
+                    .filter(|data| !data.is_empty() && *data != ",")
+                    .map(address),
+            }),
+            "closewindow" => Some(Event::CloseWindow {
+                address: address(data),
+            }),
+            "configreloaded" => Some(Event::ConfigReloaded),
+            _ => None,
+        }
+    }
+}
+
+/// What `raise watch` does to a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Focus,
+    Float,
+    Tile,
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "focus" => Ok(Action::Focus),
+            "float" => Ok(Action::Float),
+            "tile" => Ok(Action::Tile),
+            _ => Err(format!(
+                "unknown action `{action}`, expected `focus`, `float` or `tile`"
+            )),
+        }
+    }
+}
+
+impl Action {
+    /// The dispatch doing this to the window at the address, or to the active window
+    /// without one, which has nothing to focus
+    pub fn dispatch(self, address: Option<&str>) -> Option<(&'static str, String)> {
+        let argument = address.map_or_else(String::new, |address| format!("address:{address}"));
+        match self {
+            Action::Focus if address.is_none() => None,
+            Action::Focus => Some(("focuswindow", argument)),
+            Action::Float => Some(("setfloating", argument)),
+            Action::Tile => Some(("settiled", argument)),
+        }
+    }
+}
+
+/// Hand every event announced on `socket`, usually `hyprctl::event_socket_path`, to
+/// `on_event` until the socket closes or `on_event` fails
+pub fn listen(socket: &Path, mut on_event: impl FnMut(Event) -> Result<()>) -> Result<()> {
+    let stream = UnixStream::connect(socket)?;
+    for line in BufReader::new(stream).lines() {
+        if let Some(event) = Event::parse(&line?) {
+            on_event(event)?;
+        }
+    }
+    Ok(())
+}
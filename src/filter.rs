@@ -0,0 +1,257 @@
+use crate::glob::Glob;
+use crate::hyprctl::Compositor;
+use crate::{Client, RaiseError, Result};
+use miniserde::json::Value;
+use regex_lite::Regex;
+use unicode_normalization::UnicodeNormalization;
+
+/// How a client property is compared
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Exact(String),
+    Substring(String),
+    Glob(Glob),
+    /// Matching anywhere in the value, unless anchored with `^` and `$`
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn regex(pattern: &str) -> Result<Self> {
+        Regex::new(pattern).map(Pattern::Regex).map_err(|error| {
+            RaiseError::InvalidArgs(format!("pattern `{pattern}` is not a regex: {error}"))
+        })
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Pattern::Exact(exact) => value == exact,
+            Pattern::Substring(substring) => value.contains(substring.as_str()),
+            Pattern::Glob(glob) => glob.matches(value),
+            Pattern::Regex(regex) => regex.is_match(value),
+        }
+    }
+
+    /// Like `matches`, with both sides in Unicode normalization form C first, so that
+    /// composed and decomposed spellings of the same text match. Globs and regexes only
+    /// normalize the value.
+    pub fn matches_normalized(&self, value: &str) -> bool {
+        let nfc = |text: &str| text.nfc().collect::<String>();
+        match self {
+            Pattern::Exact(exact) => nfc(value) == nfc(exact),
+            Pattern::Substring(substring) => nfc(value).contains(&nfc(substring)),
+            Pattern::Glob(glob) => glob.matches(&nfc(value)),
+            Pattern::Regex(regex) => regex.is_match(&nfc(value)),
+        }
+    }
+}
+
+/// A `field=value` condition on the JSON hyprctl reports for a client, for properties
+/// without an option of their own. Nested fields are written like `workspace.name`.
+#[derive(Debug, Clone)]
+pub struct JsonMatch {
+    path: Vec<String>,
+    value: String,
+}
+
+impl JsonMatch {
+    pub fn new(condition: &str) -> Result<Self> {
+        match condition.split_once('=') {
+            Some((field, value)) if !field.is_empty() => Ok(JsonMatch {
+                path: field.split('.').map(String::from).collect(),
+                value: value.to_string(),
+            }),
+            _ => Err(RaiseError::InvalidArgs(format!(
+                "`{condition}` is not a condition, expected `field=value`"
+            ))),
+        }
+    }
+
+    /// Strings compare as they are and other values as their JSON, so `floating=true`
+    /// and `monitor=1` work as expected
+    pub fn matches(&self, client: &Value) -> bool {
+        let mut value = client;
+        for field in &self.path {
+            match value {
+                Value::Object(object) => match object.get(field) {
+                    Some(field) => value = field,
+                    None => return false,
+                },
+                _ => return false,
+            }
+        }
+        match value {
+            Value::String(string) => *string == self.value,
+            other => miniserde::json::to_string(other) == self.value,
+        }
+    }
+
+    /// Addresses of the clients that meet every condition
+    pub fn addresses(conditions: &[JsonMatch], clients: &[Value]) -> Vec<String> {
+        let matching = clients
+            .iter()
+            .filter(|client| conditions.iter().all(|condition| condition.matches(client)));
+        matching
+            .filter_map(|client| match client {
+                Value::Object(object) => match object.get("address") {
+                    Some(Value::String(address)) => Some(address.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Which clients to select, where every given criterion has to hold
+#[derive(Debug, Clone, Default)]
+pub struct ClientFilter {
+    /// The class, where `Exact("")` selects windows without one
+    pub class: Option<Pattern>,
+    /// Compare classes after Unicode normalization instead of byte for byte
+    pub normalize: bool,
+    /// Also match the class the window was created with
+    pub class_or_initial: bool,
+    pub title: Option<Pattern>,
+    /// The workspace name
+    pub workspace: Option<Pattern>,
+    pub monitor: Option<i64>,
+    /// XWayland windows with `true`, native ones with `false`
+    pub xwayland: Option<bool>,
+    /// Floating windows with `true`, tiled ones with `false`
+    pub floating: Option<bool>,
+    /// Windows on special workspaces with `true`, on regular ones with `false`
+    pub special: Option<bool>,
+    /// Leave out windows hidden because another window swallowed them
+    pub exclude_swallowed: bool,
+    /// Addresses to leave out
+    pub exclude: Vec<String>,
+    /// Addresses to restrict the selection to, when given
+    pub only: Option<Vec<String>>,
+}
+
+impl ClientFilter {
+    /// Whether a client matches on its own properties, without regard to other windows
+    pub fn matches(&self, client: &Client) -> bool {
+        let matches = |pattern: &Option<Pattern>, value: &str| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches(value))
+        };
+        let class_is = |class: &str| match &self.class {
+            Some(pattern) if self.normalize => pattern.matches_normalized(class),
+            pattern => matches(pattern, class),
+        };
+        let initial = client
+            .initial_class
+            .as_deref()
+            .filter(|_| self.class_or_initial);
+        let class_matches = class_is(&client.class) || initial.is_some_and(class_is);
+        class_matches
+            && matches(&self.title, &client.title)
+            && matches(&self.workspace, &client.workspace.name)
+            && self.monitor.is_none_or(|monitor| client.monitor == monitor)
+            && self
+                .xwayland
+                .is_none_or(|xwayland| client.xwayland == xwayland)
+            && self
+                .floating
+                .is_none_or(|floating| client.floating == floating)
+            && self
+                .special
+                .is_none_or(|special| client.is_special() == special)
+            && client.has_address()
+            && !self.exclude.contains(&client.address)
+            && self
+                .only
+                .as_ref()
+                .is_none_or(|only| only.contains(&client.address))
+    }
+
+    /// Matching clients, in the order given
+    pub fn apply<'a>(&self, clients: &'a [Client]) -> Vec<&'a Client> {
+        // Swallowed windows are hidden, focusing them shows nothing
+        let swallowed = if self.exclude_swallowed {
+            Client::swallowed(clients)
+        } else {
+            Vec::new()
+        };
+        clients
+            .iter()
+            .filter(|client| self.matches(client) && !swallowed.contains(&client.address.as_str()))
+            .collect()
+    }
+}
+
+/// Clients of the compositor that match the filter, in `hyprctl clients` order
+pub fn list_clients(hypr: &impl Compositor, filter: &ClientFilter) -> Result<Vec<Client>> {
+    let clients = hypr.clients()?;
+    Ok(filter.apply(&clients).into_iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(class: &str, address: &str) -> Client {
+        let json = format!(
+            r#"{{"class": "{class}", "title": "", "address": "{address}", "pid": 0, "monitor": 0,
+                "workspace": {{"id": 1, "name": "1"}}, "pinned": false, "xwayland": false,
+                "floating": false}}"#
+        );
+        miniserde::json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn regexes_match_anywhere_unless_anchored() {
+        let version = Pattern::regex(r"v\d+\.\d+").unwrap();
+        assert!(version.matches("Firefox v128.0"));
+        assert!(!version.matches("Firefox"));
+        let whole = Pattern::regex("^kitty$").unwrap();
+        assert!(whole.matches("kitty"));
+        assert!(!whole.matches("kitty-dropdown"));
+        assert!(Pattern::regex("^Caf\u{e9}$")
+            .unwrap()
+            .matches_normalized("Cafe\u{301}"));
+        assert!(matches!(
+            Pattern::regex("(unclosed"),
+            Err(RaiseError::InvalidArgs(_))
+        ));
+    }
+
+    #[test]
+    fn normalize_matches_decomposed_class() {
+        // "Café" with a precomposed é against an e followed by a combining acute accent
+        let client = client("Cafe\u{301}", "0x1");
+        let filter = ClientFilter {
+            class: Some(Pattern::Exact("Caf\u{e9}".into())),
+            ..ClientFilter::default()
+        };
+        assert!(!filter.matches(&client));
+        let filter = ClientFilter {
+            normalize: true,
+            ..filter
+        };
+        assert!(filter.matches(&client));
+    }
+
+    #[test]
+    fn class_or_initial_matches_before_and_after_the_class_changes() {
+        // Electron applications start out with a generic class
+        let loaded = Client {
+            initial_class: Some("electron".into()),
+            ..client("discord", "0x1")
+        };
+        let filter = ClientFilter {
+            class: Some(Pattern::Exact("electron".into())),
+            ..ClientFilter::default()
+        };
+        assert!(!filter.matches(&loaded));
+        let filter = ClientFilter {
+            class_or_initial: true,
+            ..filter
+        };
+        assert!(filter.matches(&loaded));
+        assert!(filter.matches(&client("electron", "0x2")));
+        assert!(!filter.matches(&client("slack", "0x3")));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{RaiseError, Result};
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `[a-z]`, or `[!a-z]` when negated
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+/// Shell-style pattern supporting `*`, `?` and `[...]` classes
+#[derive(Debug, Clone)]
+pub struct Glob {
+    tokens: Vec<Token>,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| RaiseError::InvalidArgs(format!("pattern `{pattern}` {reason}"));
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '?' => Token::Any,
+                '*' => Token::Star,
+                '[' => {
+                    let mut negated = false;
+                    let mut ranges = Vec::new();
+                    let mut closed = false;
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '!' if ranges.is_empty() && !negated => negated = true,
+                            ']' if !ranges.is_empty() => {
+                                closed = true;
+                                break;
+                            }
+                            // A `-` right before the closing `]` is a literal one
+                            '-' if !ranges.is_empty() && !chars.as_str().starts_with(']') => {
+                                let end =
+                                    chars.next().ok_or_else(|| invalid("has an unclosed `[`"))?;
+                                let (start, _) = ranges.pop().unwrap();
+                                if end < start {
+                                    return Err(invalid("has a reversed range"));
+                                }
+                                ranges.push((start, end));
+                            }
+                            c => ranges.push((c, c)),
+                        }
+                    }
+                    if !closed {
+                        return Err(invalid("has an unclosed `[`"));
+                    }
+                    Token::Class { negated, ranges }
+                }
+                c => Token::Literal(c),
+            };
+            tokens.push(token);
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Matched in one pass, returning to the last `*` to let it take one more character
+    /// whenever the rest fails, which keeps patterns with many stars linear per star
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.chars().collect::<Vec<_>>();
+        let (mut token, mut at) = (0, 0);
+        // The token after the last `*`, and where in the text that `*` stopped
+        let mut backtrack = None;
+        while at < text.len() {
+            match self.tokens.get(token) {
+                Some(Token::Star) => {
+                    token += 1;
+                    backtrack = Some((token, at));
+                }
+                Some(next) if next.accepts(text[at]) => {
+                    token += 1;
+                    at += 1;
+                }
+                _ => match backtrack {
+                    Some((after, stopped)) => {
+                        backtrack = Some((after, stopped + 1));
+                        (token, at) = (after, stopped + 1);
+                    }
+                    None => return false,
+                },
+            }
+        }
+        let rest = &self.tokens[token..];
+        rest.iter().all(|token| matches!(token, Token::Star))
+    }
+}
+
+impl Token {
+    /// Whether the token takes this one character, which a `*` never does on its own
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            Token::Literal(literal) => *literal == c,
+            Token::Any => true,
+            Token::Class { negated, ranges } => {
+                ranges
+                    .iter()
+                    .any(|&(start, end)| (start..=end).contains(&c))
+                    != *negated
+            }
+            Token::Star => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str) -> Glob {
+        Glob::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn trailing_dash_in_a_class_is_literal() {
+        let class = glob("[a-]");
+        assert!(class.matches("a"));
+        assert!(class.matches("-"));
+        assert!(!class.matches("b"));
+        assert!(!class.matches("]"));
+        assert!(glob("[a-c]").matches("b"));
+        assert!(glob("x[!a-]").matches("xb"));
+        assert!(!glob("x[!a-]").matches("x-"));
+    }
+
+    #[test]
+    fn stars_do_not_backtrack_exponentially() {
+        let text = "a".repeat(200);
+        assert!(!glob("*a*a*a*a*a*a*a*b").matches(&text));
+        assert!(glob("*a*a*a*a*a*a*a*").matches(&text));
+        assert!(glob("dev*").matches("dev"));
+        assert!(glob("*-*").matches("web-2"));
+        assert!(!glob("*-*").matches("web"));
+        assert!(glob("?e*[0-9]").matches("web-2"));
+    }
+}
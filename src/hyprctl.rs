@@ -0,0 +1,1031 @@
+use crate::keys::Keys;
+use crate::launch::Spawn;
+use crate::menu;
+use crate::version::{self, Version};
+use crate::{cache, Client, RaiseError, Result};
+use miniserde::{json, Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Dispatchers that move a window given as `<workspace>,address:<address>`
+pub const MOVE_DISPATCHERS: &[&str] = &["movetoworkspace", "movetoworkspacesilent"];
+
+/// What `hyprctl --batch` puts between the replies to its commands
+const BATCH_SEPARATOR: &str = "\n\n\n";
+
+/// How often clients are polled while waiting for a window
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Directories Hyprland places instance sockets in, newest location first
+fn socket_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(runtime) = std::env::var_os("XDG_RUNTIME_DIR") {
+        dirs.push(PathBuf::from(runtime).join("hypr"));
+    }
+    dirs.push(PathBuf::from("/tmp/hypr"));
+    dirs
+}
+
+/// The control socket of the instance hyprctl talks to, if it can be found
+pub fn socket_path() -> Option<PathBuf> {
+    instance_socket(".socket.sock")
+}
+
+/// The socket the same instance announces events on
+pub fn event_socket_path() -> Option<PathBuf> {
+    instance_socket(".socket2.sock")
+}
+
+fn instance_socket(name: &str) -> Option<PathBuf> {
+    let signature = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE");
+    let instances = |dir: PathBuf| -> Vec<PathBuf> {
+        match &signature {
+            Some(signature) => vec![dir.join(signature)],
+            None => {
+                let entries = dir.read_dir().into_iter().flatten().flatten();
+                entries.map(|entry| entry.path()).collect()
+            }
+        }
+    };
+    socket_dirs()
+        .into_iter()
+        .flat_map(instances)
+        .map(|instance| instance.join(name))
+        .find(|socket| socket.exists())
+}
+
+/// Signatures of the running instances, found by their sockets
+pub fn instances() -> Vec<String> {
+    let mut signatures = Vec::new();
+    for dir in socket_dirs() {
+        for entry in dir.read_dir().into_iter().flatten().flatten() {
+            let signature = entry.file_name().to_string_lossy().into_owned();
+            if entry.path().join(".socket.sock").exists() && !signatures.contains(&signature) {
+                signatures.push(signature);
+            }
+        }
+    }
+    signatures
+}
+
+/// Whether hyprctl has an instance to talk to, either through the environment or by
+/// finding a running instance's socket on its own
+pub fn check_instance() -> Result<()> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() || socket_path().is_some() {
+        Ok(())
+    } else {
+        Err(RaiseError::NoCompositor)
+    }
+}
+
+fn spawn_error(error: std::io::Error) -> RaiseError {
+    match error.kind() {
+        ErrorKind::NotFound => RaiseError::HyprctlNotFound,
+        _ => RaiseError::Io(error),
+    }
+}
+
+/// hyprctl, talking to the given instance rather than the one in the environment
+fn hyprctl(instance: Option<&str>) -> Command {
+    let mut command = Command::new("hyprctl");
+    if let Some(signature) = instance {
+        command.arg("--instance").arg(signature);
+    }
+    command
+}
+
+/// Run a `hyprctl -j` query and parse its output
+pub fn query<T: Deserialize>(command: &str) -> Result<T> {
+    query_on(None, command)
+}
+
+fn query_on<T: Deserialize>(instance: Option<&str>, command: &str) -> Result<T> {
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = hyprctl(instance)
+        .arg(command)
+        .arg("-j")
+        .output()
+        .map_err(spawn_error)?;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr).into_owned();
+        return Err(RaiseError::HyprctlFailed { status, stderr });
+    }
+    let parse_failed = || RaiseError::ParseFailed(format!("hyprctl {command} -j"));
+    let stdout = String::from_utf8(stdout).map_err(|_| parse_failed())?;
+    json::from_str::<T>(&stdout).map_err(|_| parse_failed())
+}
+
+/// Run a JSON query by writing it to the control socket directly, which is what hyprctl
+/// does without the cost of spawning it
+#[cfg(unix)]
+pub fn query_socket<T: Deserialize>(command: &str) -> Result<T> {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    let socket = socket_path().ok_or(RaiseError::NoCompositor)?;
+    let mut stream = UnixStream::connect(socket)?;
+    stream.write_all(format!("j/{command}").as_bytes())?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    json::from_str::<T>(&reply).map_err(|_| RaiseError::ParseFailed(format!("j/{command}")))
+}
+
+pub fn clients() -> Result<Vec<Client>> {
+    query("clients")
+}
+
+pub fn active_window() -> Result<Client> {
+    query("activewindow")
+}
+
+/// The clients and the active window from a single `hyprctl --batch`, which saves a
+/// round trip when both are needed. The active window fails like `active_window` does.
+pub fn fetch_state() -> Result<(Vec<Client>, Result<Client>)> {
+    fetch_state_on(None)
+}
+
+fn fetch_state_on(instance: Option<&str>) -> Result<(Vec<Client>, Result<Client>)> {
+    let commands = "j/clients ; j/activewindow";
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = hyprctl(instance)
+        .arg("--batch")
+        .arg(commands)
+        .output()
+        .map_err(spawn_error)?;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr).into_owned();
+        return Err(RaiseError::HyprctlFailed { status, stderr });
+    }
+    let parse_failed = || RaiseError::ParseFailed(format!("hyprctl --batch {commands}"));
+    let stdout = String::from_utf8(stdout).map_err(|_| parse_failed())?;
+    let mut replies = stdout.split(BATCH_SEPARATOR);
+    let clients = replies.next().and_then(|reply| json::from_str(reply).ok());
+    let active = replies.next().and_then(|reply| json::from_str(reply).ok());
+    Ok((
+        clients.ok_or_else(parse_failed)?,
+        active.ok_or_else(parse_failed),
+    ))
+}
+
+/// Run a dispatching hyprctl, which replies `ok` once for every dispatch it applied
+fn acknowledged(mut command: Command, description: String) -> Result<()> {
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = command.output().map_err(spawn_error)?;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr).into_owned();
+        return Err(RaiseError::HyprctlFailed { status, stderr });
+    }
+    let reply = String::from_utf8_lossy(&stdout);
+    if reply
+        .lines()
+        .map(str::trim)
+        .all(|line| line.is_empty() || line == "ok")
+    {
+        Ok(())
+    } else {
+        Err(RaiseError::DispatchFailed {
+            command: description,
+            reply: reply.trim().to_string(),
+        })
+    }
+}
+
+/// Dispatches wait for hyprctl so that chained dispatches arrive in order
+pub fn dispatch(dispatcher: &str, argument: &str) -> Result<()> {
+    dispatch_on(None, dispatcher, argument)
+}
+
+fn dispatch_on(instance: Option<&str>, dispatcher: &str, argument: &str) -> Result<()> {
+    let mut command = hyprctl(instance);
+    command.arg("dispatch").arg(dispatcher);
+    if !argument.is_empty() {
+        command.arg(argument);
+    }
+    let description = format!("dispatch {dispatcher} {argument}")
+        .trim_end()
+        .to_string();
+    acknowledged(command, description)
+}
+
+/// Run several dispatches with a single `hyprctl --batch`
+pub fn dispatch_batch(dispatches: &[(&str, String)]) -> Result<()> {
+    dispatch_batch_on(None, dispatches)
+}
+
+fn dispatch_batch_on(instance: Option<&str>, dispatches: &[(&str, String)]) -> Result<()> {
+    let batch = dispatches
+        .iter()
+        .map(|(dispatcher, argument)| format!("dispatch {dispatcher} {argument}"))
+        .collect::<Vec<_>>()
+        .join(" ; ");
+    let mut command = hyprctl(instance);
+    command.arg("--batch").arg(&batch);
+    acknowledged(command, format!("--batch {batch}"))
+}
+
+/// How a window gets focused
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FocusMethod {
+    /// A single `focuswindow`
+    #[default]
+    #[serde(rename = "plain")]
+    Plain,
+    /// `alterzorder top` before `focuswindow`, for floating windows that are focused
+    /// without being visibly raised
+    #[serde(rename = "raise")]
+    Raise,
+}
+
+impl FromStr for FocusMethod {
+    type Err = String;
+
+    fn from_str(method: &str) -> Result<Self, Self::Err> {
+        match method {
+            "plain" => Ok(FocusMethod::Plain),
+            "raise" => Ok(FocusMethod::Raise),
+            _ => Err(format!(
+                "unknown focus method `{method}`, expected `plain` or `raise`"
+            )),
+        }
+    }
+}
+
+impl FocusMethod {
+    /// The dispatches that focus a window this way
+    pub fn dispatches(self, address: &str) -> Vec<(&'static str, String)> {
+        let focus = ("focuswindow", format!("address:{address}"));
+        match self {
+            FocusMethod::Plain => vec![focus],
+            FocusMethod::Raise => vec![("alterzorder", format!("top,address:{address}")), focus],
+        }
+    }
+}
+
+/// Everything raise needs from the compositor, so that decisions can be tested against a
+/// fake one
+pub trait Compositor {
+    /// Whether there is an instance to talk to at all
+    fn check_instance(&self) -> Result<()>;
+    fn clients(&self) -> Result<Vec<Client>>;
+    fn active_window(&self) -> Result<Client>;
+    /// Clients as untyped JSON, with every field hyprctl reports
+    fn client_values(&self) -> Result<Vec<json::Value>>;
+    fn version(&self) -> Result<Version>;
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()>;
+    /// Several dispatches applied in order, in one round trip where possible
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()>;
+
+    /// The clients along with the active window, for when both are needed
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        Ok((self.clients()?, self.active_window()))
+    }
+
+    fn launch(&self, command: &str) -> Result<()> {
+        self.dispatch("exec", command)
+    }
+
+    /// Start a program raise spawns itself. Hyprland plays no part in it, but going
+    /// through here lets `--plan` leave it out along with the dispatches.
+    fn spawn(&self, program: &Spawn) -> Result<()> {
+        program.spawn()
+    }
+
+    /// Type keys into the focused window with `wtype`, which goes through here for the
+    /// same reason
+    fn type_keys(&self, keys: &Keys) -> Result<()> {
+        keys.wtype()
+    }
+
+    /// Let the user pick one of the clients with a dmenu-style menu, which goes through
+    /// here so that `--plan` does not show it
+    fn choose<'a>(&self, command: &str, clients: &[&'a Client]) -> Result<Option<&'a Client>> {
+        menu::choose(command, clients)
+    }
+
+    fn focus_window(&self, address: &str) -> Result<()> {
+        self.dispatch("focuswindow", &format!("address:{address}"))
+    }
+
+    fn focus_window_with(&self, address: &str, method: FocusMethod) -> Result<()> {
+        match method {
+            FocusMethod::Plain => self.focus_window(address),
+            FocusMethod::Raise => self.dispatch_batch(&method.dispatches(address)),
+        }
+    }
+
+    /// Bring windows to the top of the z-order, in order, so the last one ends up on top
+    fn raise_to_top<'a>(&self, addresses: impl IntoIterator<Item = &'a str>) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let dispatches = addresses
+            .into_iter()
+            .map(|address| ("alterzorder", format!("top,address:{address}")))
+            .collect::<Vec<_>>();
+        self.dispatch_batch(&dispatches)
+    }
+
+    /// Poll until a matching window appears that is not among `known`, or `None` on
+    /// timeout
+    fn wait_for_window(
+        &self,
+        matches: impl Fn(&Client) -> bool,
+        known: &[Client],
+        timeout: Duration,
+    ) -> Result<Option<Client>>
+    where
+        Self: Sized,
+    {
+        let start = Instant::now();
+        loop {
+            let new = self.clients()?.into_iter().find(|client| {
+                matches(client) && known.iter().all(|old| old.address != client.address)
+            });
+            if new.is_some() || start.elapsed() >= timeout {
+                return Ok(new);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Poll until the window is the active one, returning whether it was within the timeout
+    fn wait_for_focus(&self, address: &str, timeout: Duration) -> Result<bool> {
+        let start = Instant::now();
+        loop {
+            if self
+                .active_window()
+                .is_ok_and(|client| client.address == address)
+            {
+                return Ok(true);
+            }
+            if start.elapsed() >= timeout {
+                return Ok(false);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Poll until the window is gone, returning whether it went within the timeout
+    fn wait_for_close(&self, address: &str, timeout: Duration) -> Result<bool> {
+        let start = Instant::now();
+        loop {
+            if self
+                .clients()?
+                .iter()
+                .all(|client| client.address != address)
+            {
+                return Ok(true);
+            }
+            if start.elapsed() >= timeout {
+                return Ok(false);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// A compositor wrapped around another, like `BestEffort` or `Cached`, which forwards
+/// whatever it does not override to the inner one. Wrappers implement this rather than
+/// `Compositor`, so that a method they leave out goes to the inner compositor instead of
+/// to the default of `Compositor`, which would bypass it.
+pub trait Delegate {
+    type Inner: Compositor;
+
+    /// The compositor to forward to
+    fn inner(&self) -> Result<&Self::Inner>;
+
+    fn check_instance(&self) -> Result<()> {
+        self.inner()?.check_instance()
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        self.inner()?.clients()
+    }
+
+    fn active_window(&self) -> Result<Client> {
+        self.inner()?.active_window()
+    }
+
+    fn client_values(&self) -> Result<Vec<json::Value>> {
+        self.inner()?.client_values()
+    }
+
+    fn version(&self) -> Result<Version> {
+        self.inner()?.version()
+    }
+
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()> {
+        self.inner()?.dispatch(dispatcher, argument)
+    }
+
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()> {
+        self.inner()?.dispatch_batch(dispatches)
+    }
+
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        self.inner()?.state()
+    }
+
+    fn launch(&self, command: &str) -> Result<()> {
+        self.inner()?.launch(command)
+    }
+
+    fn spawn(&self, program: &Spawn) -> Result<()> {
+        self.inner()?.spawn(program)
+    }
+
+    fn type_keys(&self, keys: &Keys) -> Result<()> {
+        self.inner()?.type_keys(keys)
+    }
+
+    fn choose<'a>(&self, command: &str, clients: &[&'a Client]) -> Result<Option<&'a Client>> {
+        self.inner()?.choose(command, clients)
+    }
+
+    fn focus_window(&self, address: &str) -> Result<()> {
+        self.inner()?.focus_window(address)
+    }
+
+    fn focus_window_with(&self, address: &str, method: FocusMethod) -> Result<()> {
+        self.inner()?.focus_window_with(address, method)
+    }
+
+    fn raise_to_top<'a>(&self, addresses: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        self.inner()?.raise_to_top(addresses)
+    }
+
+    fn wait_for_window(
+        &self,
+        matches: impl Fn(&Client) -> bool,
+        known: &[Client],
+        timeout: Duration,
+    ) -> Result<Option<Client>> {
+        self.inner()?.wait_for_window(matches, known, timeout)
+    }
+
+    fn wait_for_focus(&self, address: &str, timeout: Duration) -> Result<bool> {
+        self.inner()?.wait_for_focus(address, timeout)
+    }
+
+    fn wait_for_close(&self, address: &str, timeout: Duration) -> Result<bool> {
+        self.inner()?.wait_for_close(address, timeout)
+    }
+}
+
+impl<D: Delegate> Compositor for D {
+    fn check_instance(&self) -> Result<()> {
+        Delegate::check_instance(self)
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        Delegate::clients(self)
+    }
+
+    fn active_window(&self) -> Result<Client> {
+        Delegate::active_window(self)
+    }
+
+    fn client_values(&self) -> Result<Vec<json::Value>> {
+        Delegate::client_values(self)
+    }
+
+    fn version(&self) -> Result<Version> {
+        Delegate::version(self)
+    }
+
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()> {
+        Delegate::dispatch(self, dispatcher, argument)
+    }
+
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()> {
+        Delegate::dispatch_batch(self, dispatches)
+    }
+
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        Delegate::state(self)
+    }
+
+    fn launch(&self, command: &str) -> Result<()> {
+        Delegate::launch(self, command)
+    }
+
+    fn spawn(&self, program: &Spawn) -> Result<()> {
+        Delegate::spawn(self, program)
+    }
+
+    fn type_keys(&self, keys: &Keys) -> Result<()> {
+        Delegate::type_keys(self, keys)
+    }
+
+    fn choose<'a>(&self, command: &str, clients: &[&'a Client]) -> Result<Option<&'a Client>> {
+        Delegate::choose(self, command, clients)
+    }
+
+    fn focus_window(&self, address: &str) -> Result<()> {
+        Delegate::focus_window(self, address)
+    }
+
+    fn focus_window_with(&self, address: &str, method: FocusMethod) -> Result<()> {
+        Delegate::focus_window_with(self, address, method)
+    }
+
+    fn raise_to_top<'a>(&self, addresses: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        Delegate::raise_to_top(self, addresses)
+    }
+
+    fn wait_for_window(
+        &self,
+        matches: impl Fn(&Client) -> bool,
+        known: &[Client],
+        timeout: Duration,
+    ) -> Result<Option<Client>> {
+        Delegate::wait_for_window(self, matches, known, timeout)
+    }
+
+    fn wait_for_focus(&self, address: &str, timeout: Duration) -> Result<bool> {
+        Delegate::wait_for_focus(self, address, timeout)
+    }
+
+    fn wait_for_close(&self, address: &str, timeout: Duration) -> Result<bool> {
+        Delegate::wait_for_close(self, address, timeout)
+    }
+}
+
+/// The running Hyprland, through the `hyprctl` binary
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hyprctl;
+
+impl Compositor for Hyprctl {
+    fn check_instance(&self) -> Result<()> {
+        check_instance()
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        clients()
+    }
+
+    fn active_window(&self) -> Result<Client> {
+        active_window()
+    }
+
+    fn client_values(&self) -> Result<Vec<json::Value>> {
+        query("clients")
+    }
+
+    fn version(&self) -> Result<Version> {
+        version::detect()
+    }
+
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()> {
+        dispatch(dispatcher, argument)
+    }
+
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()> {
+        dispatch_batch(dispatches)
+    }
+
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        fetch_state()
+    }
+}
+
+/// Another compositor whose failed dispatches are only reported under `verbose`, for
+/// callers that would rather carry on than stop at the first one
+#[derive(Debug, Clone, Copy)]
+pub struct BestEffort<C> {
+    pub inner: C,
+    pub verbose: bool,
+}
+
+impl<C: Compositor> BestEffort<C> {
+    fn ignore(&self, result: Result<()>) -> Result<()> {
+        if let Err(error) = result {
+            if self.verbose {
+                eprintln!("Ignoring failed dispatch: {error}");
+            }
+        }
+        Ok(())
+    }
+}
+
+// Programs raise starts itself are not dispatches, so failing to start them still fails
+impl<C: Compositor> Delegate for BestEffort<C> {
+    type Inner = C;
+
+    fn inner(&self) -> Result<&C> {
+        Ok(&self.inner)
+    }
+
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()> {
+        self.ignore(self.inner.dispatch(dispatcher, argument))
+    }
+
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()> {
+        self.ignore(self.inner.dispatch_batch(dispatches))
+    }
+
+    fn launch(&self, command: &str) -> Result<()> {
+        self.ignore(self.inner.launch(command))
+    }
+
+    fn focus_window(&self, address: &str) -> Result<()> {
+        self.ignore(self.inner.focus_window(address))
+    }
+
+    fn focus_window_with(&self, address: &str, method: FocusMethod) -> Result<()> {
+        self.ignore(self.inner.focus_window_with(address, method))
+    }
+
+    fn raise_to_top<'a>(&self, addresses: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        self.ignore(self.inner.raise_to_top(addresses))
+    }
+}
+
+/// Reports the classes of clients through a table of aliases, so that an application
+/// whose class differs between versions, or between XWayland and native builds, goes by
+/// one canonical class everywhere a class is compared
+pub struct ClassMap<C> {
+    pub inner: C,
+    /// Canonical class by reported class, where unlisted classes stay as they are
+    pub classes: BTreeMap<String, String>,
+}
+
+impl<C> ClassMap<C> {
+    fn canonical(&self, mut client: Client) -> Client {
+        let alias = |class: &mut String| {
+            if let Some(canonical) = self.classes.get(class) {
+                *class = canonical.clone();
+            }
+        };
+        alias(&mut client.class);
+        if let Some(initial) = client.initial_class.as_mut() {
+            alias(initial);
+        }
+        client
+    }
+}
+
+// The JSON is left as reported, `--match-json` compares the raw class
+impl<C: Compositor> Delegate for ClassMap<C> {
+    type Inner = C;
+
+    fn inner(&self) -> Result<&C> {
+        Ok(&self.inner)
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        let clients = self.inner.clients()?;
+        Ok(clients
+            .into_iter()
+            .map(|client| self.canonical(client))
+            .collect())
+    }
+
+    fn active_window(&self) -> Result<Client> {
+        self.inner
+            .active_window()
+            .map(|client| self.canonical(client))
+    }
+
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        let (clients, active) = self.inner.state()?;
+        let clients = clients
+            .into_iter()
+            .map(|client| self.canonical(client))
+            .collect();
+        Ok((clients, active.map(|client| self.canonical(client))))
+    }
+
+    // Windows are matched and handed back by their canonical class
+    fn wait_for_window(
+        &self,
+        matches: impl Fn(&Client) -> bool,
+        known: &[Client],
+        timeout: Duration,
+    ) -> Result<Option<Client>> {
+        let matches = |client: &Client| matches(&self.canonical(client.clone()));
+        let new = self.inner.wait_for_window(matches, known, timeout)?;
+        Ok(new.map(|client| self.canonical(client)))
+    }
+}
+
+/// Another compositor whose clients are kept in a file for `ttl` milliseconds, so that
+/// invocations in quick succession share one query. The file is that of the instance with
+/// the signature, and holds clients as the instance reports them, before a `ClassMap`
+/// above rewrites their classes. Anything that changes windows drops it, even without a
+/// `ttl`, so that other invocations do not go by what it was before.
+pub struct Cached<C> {
+    pub inner: C,
+    pub dir: PathBuf,
+    /// Without a signature nothing is cached
+    pub signature: Option<String>,
+    pub ttl: Option<u64>,
+}
+
+impl<C> Cached<C> {
+    fn invalidate(&self) {
+        if let Some(signature) = &self.signature {
+            cache::invalidate(&self.dir, signature);
+        }
+    }
+
+    fn caching(&self) -> Option<(&Path, &str, u64)> {
+        let signature = self.signature.as_deref()?;
+        Some((&self.dir, signature, self.ttl?))
+    }
+}
+
+// Waiting watches windows change, which a cache would hide, so it goes to the inner
+// compositor like looking up the active window does
+impl<C: Compositor> Delegate for Cached<C> {
+    type Inner = C;
+
+    fn inner(&self) -> Result<&C> {
+        Ok(&self.inner)
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        let Some((dir, signature, ttl)) = self.caching() else {
+            return self.inner.clients();
+        };
+        if let Some(clients) = cache::load(dir, signature, ttl) {
+            return Ok(clients);
+        }
+        let clients = self.inner.clients()?;
+        cache::store(dir, signature, &clients);
+        Ok(clients)
+    }
+
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()> {
+        self.invalidate();
+        self.inner.dispatch(dispatcher, argument)
+    }
+
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()> {
+        self.invalidate();
+        self.inner.dispatch_batch(dispatches)
+    }
+
+    // The active window is never cached, only the clients that come with it
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        if self.caching().is_none() {
+            return self.inner.state();
+        }
+        Ok((Delegate::clients(self)?, self.inner.active_window()))
+    }
+
+    fn launch(&self, command: &str) -> Result<()> {
+        self.invalidate();
+        self.inner.launch(command)
+    }
+
+    fn spawn(&self, program: &Spawn) -> Result<()> {
+        self.invalidate();
+        self.inner.spawn(program)
+    }
+
+    fn focus_window(&self, address: &str) -> Result<()> {
+        self.invalidate();
+        self.inner.focus_window(address)
+    }
+
+    fn focus_window_with(&self, address: &str, method: FocusMethod) -> Result<()> {
+        self.invalidate();
+        self.inner.focus_window_with(address, method)
+    }
+
+    fn raise_to_top<'a>(&self, addresses: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        self.invalidate();
+        self.inner.raise_to_top(addresses)
+    }
+}
+
+/// A running Hyprland picked by its signature, rather than through the environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instance(pub String);
+
+impl Compositor for Instance {
+    fn check_instance(&self) -> Result<()> {
+        let sockets = socket_dirs()
+            .into_iter()
+            .map(|dir| dir.join(&self.0).join(".socket.sock"));
+        if sockets.into_iter().any(|socket| socket.exists()) {
+            Ok(())
+        } else {
+            Err(RaiseError::NoCompositor)
+        }
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        query_on(Some(&self.0), "clients")
+    }
+
+    fn active_window(&self) -> Result<Client> {
+        query_on(Some(&self.0), "activewindow")
+    }
+
+    fn client_values(&self) -> Result<Vec<json::Value>> {
+        query_on(Some(&self.0), "clients")
+    }
+
+    // The cached version is that of the instance in the environment
+    fn version(&self) -> Result<Version> {
+        query_on(Some(&self.0), "version")
+    }
+
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()> {
+        dispatch_on(Some(&self.0), dispatcher, argument)
+    }
+
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()> {
+        dispatch_batch_on(Some(&self.0), dispatches)
+    }
+
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        fetch_state_on(Some(&self.0))
+    }
+}
+
+/// Several instances as one, like on a multi-seat machine. Clients of the first instance
+/// are listed first, dispatches about a window go to the instance it was listed by, and
+/// everything else, like launching, goes to the first instance.
+#[derive(Debug)]
+pub struct AllInstances<C> {
+    instances: Vec<C>,
+    /// Which instance listed each address
+    owners: RefCell<HashMap<String, usize>>,
+}
+
+impl AllInstances<Instance> {
+    /// Every running instance, the one in the environment first
+    pub fn discover() -> Self {
+        let current = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").ok();
+        let mut signatures = instances();
+        if let Some(current) = current {
+            signatures.retain(|signature| *signature != current);
+            signatures.insert(0, current);
+        }
+        Self::new(signatures.into_iter().map(Instance).collect())
+    }
+}
+
+impl<C: Compositor> AllInstances<C> {
+    pub fn new(instances: Vec<C>) -> Self {
+        AllInstances {
+            instances,
+            owners: RefCell::default(),
+        }
+    }
+
+    pub fn into_instances(self) -> Vec<C> {
+        self.instances
+    }
+
+    fn first(&self) -> Result<&C> {
+        self.instances.first().ok_or(RaiseError::NoCompositor)
+    }
+
+    /// Which instance listed the window a dispatch argument like `top,address:0x1` is
+    /// about, if any did
+    fn owner(&self, argument: &str) -> Option<usize> {
+        let address = argument
+            .split([',', ' '])
+            .find_map(|part| part.strip_prefix("address:"))?;
+        self.owners.borrow().get(address).copied()
+    }
+
+    /// The instance that listed the window with the address, or the first one
+    fn instance_of(&self, address: &str) -> Result<&C> {
+        match self.owners.borrow().get(address) {
+            Some(&index) => Ok(&self.instances[index]),
+            None => self.first(),
+        }
+    }
+
+    /// Concatenate what every instance reports, skipping instances that fail as long as
+    /// one of them answers
+    fn gather<T, F>(&self, mut query: F) -> Result<Vec<T>>
+    where
+        F: FnMut(usize, &C) -> Result<Vec<T>>,
+    {
+        let mut gathered = Vec::new();
+        let mut failure = None;
+        let mut answered = false;
+        for (index, instance) in self.instances.iter().enumerate() {
+            match query(index, instance) {
+                Ok(items) => {
+                    answered = true;
+                    gathered.extend(items);
+                }
+                Err(error) => failure = failure.or(Some(error)),
+            }
+        }
+        match failure {
+            Some(error) if !answered => Err(error),
+            _ if self.instances.is_empty() => Err(RaiseError::NoCompositor),
+            _ => Ok(gathered),
+        }
+    }
+}
+
+// Launching, like the active window, the version and whatever else is about no window in
+// particular, goes to the first instance, which is the one being used. Launched windows
+// appear there, so waiting for one does too.
+impl<C: Compositor> Delegate for AllInstances<C> {
+    type Inner = C;
+
+    fn inner(&self) -> Result<&C> {
+        self.first()
+    }
+
+    fn check_instance(&self) -> Result<()> {
+        self.first().map(|_| ())
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        self.gather(|index, instance| {
+            let clients = instance.clients()?;
+            let mut owners = self.owners.borrow_mut();
+            for client in &clients {
+                owners.entry(client.address.clone()).or_insert(index);
+            }
+            Ok(clients)
+        })
+    }
+
+    fn client_values(&self) -> Result<Vec<json::Value>> {
+        self.gather(|_, instance| instance.client_values())
+    }
+
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()> {
+        match self.owner(argument) {
+            Some(index) => self.instances[index].dispatch(dispatcher, argument),
+            None => self.first()?.dispatch(dispatcher, argument),
+        }
+    }
+
+    /// Batches are split into runs about windows of one instance, each sent there in
+    /// order. Dispatches about no window, like `centerwindow`, stay with the run before,
+    /// which they follow up on.
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()> {
+        let mut runs: Vec<(usize, Vec<(&str, String)>)> = Vec::new();
+        for (dispatcher, argument) in dispatches {
+            let previous = runs.last().map(|(index, _)| *index);
+            let index = self.owner(argument).or(previous).unwrap_or(0);
+            let dispatch = (*dispatcher, argument.clone());
+            match runs.last_mut() {
+                Some((last, run)) if *last == index => run.push(dispatch),
+                _ => runs.push((index, vec![dispatch])),
+            }
+        }
+        for (index, run) in runs {
+            let instance = self.instances.get(index).ok_or(RaiseError::NoCompositor)?;
+            instance.dispatch_batch(&run)?;
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        Ok((Delegate::clients(self)?, Delegate::active_window(self)))
+    }
+
+    fn focus_window(&self, address: &str) -> Result<()> {
+        self.instance_of(address)?.focus_window(address)
+    }
+
+    fn focus_window_with(&self, address: &str, method: FocusMethod) -> Result<()> {
+        self.instance_of(address)?
+            .focus_window_with(address, method)
+    }
+
+    /// Each instance raises its own windows, in order
+    fn raise_to_top<'a>(&self, addresses: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        let dispatches = addresses
+            .into_iter()
+            .map(|address| ("alterzorder", format!("top,address:{address}")))
+            .collect::<Vec<_>>();
+        Delegate::dispatch_batch(self, &dispatches)
+    }
+
+    fn wait_for_focus(&self, address: &str, timeout: Duration) -> Result<bool> {
+        self.instance_of(address)?.wait_for_focus(address, timeout)
+    }
+
+    fn wait_for_close(&self, address: &str, timeout: Duration) -> Result<bool> {
+        self.instance_of(address)?.wait_for_close(address, timeout)
+    }
+}
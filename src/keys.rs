@@ -0,0 +1,139 @@
+use crate::hyprctl::Compositor;
+use crate::launch::find_in_path;
+use crate::{RaiseError, Result};
+use miniserde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+
+/// A key combination like `ctrl+l`, `ctrl+shift+t` or `Return`, where the key is an XKB
+/// key name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keys {
+    pub modifiers: Vec<String>,
+    pub key: String,
+}
+
+impl FromStr for Keys {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+        if key.is_empty() || parts.iter().any(|modifier| modifier.is_empty()) {
+            return Err(format!(
+                "`{spec}` is not a key combination, expected like `ctrl+l`"
+            ));
+        }
+        Ok(Keys {
+            modifiers: parts.into_iter().map(str::to_lowercase).collect(),
+            key: key.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Keys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{modifier}+")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl Keys {
+    /// The argument of Hyprland's `sendshortcut`, which sends to the window directly
+    pub fn shortcut(&self, address: &str) -> String {
+        let modifiers = self.modifiers.join(" ").to_uppercase();
+        format!("{modifiers}, {}, address:{address}", self.key)
+    }
+
+    /// Arguments of `wtype`, which types into whatever window has focus
+    pub fn wtype_args(&self) -> Vec<String> {
+        let press = self.modifiers.iter().flat_map(|modifier| ["-M", modifier]);
+        let release = self
+            .modifiers
+            .iter()
+            .rev()
+            .flat_map(|modifier| ["-m", modifier]);
+        let key = ["-k", self.key.as_str()];
+        press.chain(key).chain(release).map(String::from).collect()
+    }
+
+    /// Send the keys to a window, which `wtype` needs to be focused already
+    pub fn send(&self, hypr: &impl Compositor, tool: KeysTool, address: &str) -> Result<()> {
+        match tool {
+            KeysTool::Hyprctl => hypr.dispatch("sendshortcut", &self.shortcut(address)),
+            KeysTool::Wtype => hypr.type_keys(self),
+        }
+    }
+
+    /// Type the keys with `wtype`
+    pub fn wtype(&self) -> Result<()> {
+        if find_in_path("wtype").is_none() {
+            let message = "--keys-tool wtype needs `wtype`, which is not in PATH";
+            return Err(RaiseError::InvalidArgs(message.into()));
+        }
+        let status = Command::new("wtype").args(self.wtype_args()).status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(RaiseError::KeysFailed {
+                keys: self.to_string(),
+                status,
+            })
+        }
+    }
+}
+
+/// What sends `--send-keys`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeysTool {
+    /// Hyprland, through `hyprctl dispatch sendshortcut`
+    #[default]
+    #[serde(rename = "hyprctl")]
+    Hyprctl,
+    /// The `wtype` virtual keyboard
+    #[serde(rename = "wtype")]
+    Wtype,
+}
+
+impl FromStr for KeysTool {
+    type Err = String;
+
+    fn from_str(tool: &str) -> Result<Self, Self::Err> {
+        match tool {
+            "hyprctl" => Ok(KeysTool::Hyprctl),
+            "wtype" => Ok(KeysTool::Wtype),
+            _ => Err(format!(
+                "unknown keys tool `{tool}`, expected `hyprctl` or `wtype`"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wtype_releases_modifiers_in_reverse() {
+        let keys: Keys = "ctrl+shift+Return".parse().unwrap();
+        assert_eq!(keys.to_string(), "ctrl+shift+Return");
+        assert_eq!(
+            keys.wtype_args(),
+            ["-M", "ctrl", "-M", "shift", "-k", "Return", "-m", "shift", "-m", "ctrl"]
+        );
+    }
+
+    #[test]
+    fn combinations_become_shortcuts_for_the_window() {
+        let keys: Keys = "Ctrl + Shift+t".parse().unwrap();
+        assert_eq!(keys.shortcut("0x1"), "CTRL SHIFT, t, address:0x1");
+        let key: Keys = "Return".parse().unwrap();
+        assert_eq!(key.shortcut("0x1"), ", Return, address:0x1");
+        for spec in ["", "ctrl+", "+l", "ctrl++l"] {
+            assert!(spec.parse::<Keys>().is_err(), "{spec}");
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use crate::hyprctl::Compositor;
+use crate::{RaiseError, Result};
+use miniserde::de::{Seq, Visitor};
+use miniserde::ser::Fragment;
+use miniserde::{make_place, Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// How to start an application
+#[derive(Debug, Clone, Copy)]
+pub enum Launch<'a> {
+    /// A shell command run by Hyprland through `hyprctl dispatch exec`. Hyprland is the
+    /// parent, so the application is always independent of raise.
+    Exec(&'a str),
+    /// A program spawned by raise itself
+    Spawn(Spawn<'a>),
+}
+
+/// A program for raise to spawn. Without `detach` it stays in raise's session and shares
+/// its stdio, so it goes down with the terminal raise was started from. With `detach` it
+/// leads a session of its own. Its output goes to `log` when given.
+#[derive(Debug, Clone, Copy)]
+pub struct Spawn<'a> {
+    pub argv: &'a [String],
+    pub detach: bool,
+    pub log: Option<&'a File>,
+    /// The working directory, raise's own when not given
+    pub cwd: Option<&'a Path>,
+}
+
+impl Launch<'_> {
+    /// The command as it would be typed, for messages
+    pub fn command_line(&self) -> String {
+        match *self {
+            Launch::Exec(command) => command.to_string(),
+            Launch::Spawn(program) => program.argv.join(" "),
+        }
+    }
+
+    pub fn run(&self, hypr: &impl Compositor) -> Result<()> {
+        match *self {
+            Launch::Exec(command) => hypr.launch(command),
+            Launch::Spawn(program) => hypr.spawn(&program),
+        }
+    }
+}
+
+/// The `launch` setting. A string is a shell command, started by the launcher. A list in
+/// the configuration file is the program and its arguments, spawned by raise directly
+/// without a shell, so nothing needs quoting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchCommand {
+    Shell(String),
+    Argv(Vec<String>),
+}
+
+impl LaunchCommand {
+    /// Check that a list names a program that can be run
+    pub fn validate(&self) -> Result<()> {
+        let LaunchCommand::Argv(argv) = self else {
+            return Ok(());
+        };
+        let Some(program) = argv.first() else {
+            return Err(RaiseError::InvalidArgs("the `launch` list is empty".into()));
+        };
+        let found = if program.contains('/') {
+            Path::new(program).is_file()
+        } else {
+            find_in_path(program).is_some()
+        };
+        if !found {
+            let message = format!("`{program}`, the first of the `launch` list, is not in PATH");
+            return Err(RaiseError::InvalidArgs(message));
+        }
+        Ok(())
+    }
+}
+
+impl Deserialize for LaunchCommand {
+    fn begin(out: &mut Option<Self>) -> &mut dyn Visitor {
+        make_place!(Place);
+
+        impl Visitor for Place<LaunchCommand> {
+            fn string(&mut self, command: &str) -> miniserde::Result<()> {
+                self.out = Some(LaunchCommand::Shell(command.into()));
+                Ok(())
+            }
+
+            fn seq(&mut self) -> miniserde::Result<Box<dyn Seq + '_>> {
+                Ok(Box::new(Arguments {
+                    out: &mut self.out,
+                    argv: Vec::new(),
+                    element: None,
+                }))
+            }
+        }
+
+        /// Collects the list, one element at a time
+        struct Arguments<'a> {
+            out: &'a mut Option<LaunchCommand>,
+            argv: Vec<String>,
+            element: Option<String>,
+        }
+
+        impl Seq for Arguments<'_> {
+            fn element(&mut self) -> miniserde::Result<&mut dyn Visitor> {
+                self.argv.extend(self.element.take());
+                Ok(Deserialize::begin(&mut self.element))
+            }
+
+            fn finish(&mut self) -> miniserde::Result<()> {
+                self.argv.extend(self.element.take());
+                *self.out = Some(LaunchCommand::Argv(std::mem::take(&mut self.argv)));
+                Ok(())
+            }
+        }
+
+        Place::new(out)
+    }
+}
+
+impl Serialize for LaunchCommand {
+    fn begin(&self) -> Fragment<'_> {
+        match self {
+            LaunchCommand::Shell(command) => command.begin(),
+            LaunchCommand::Argv(argv) => argv.begin(),
+        }
+    }
+}
+
+/// What starts `--launch` commands
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Launcher {
+    /// Hyprland, through `hyprctl dispatch exec`
+    #[default]
+    #[serde(rename = "hyprctl")]
+    Hyprctl,
+    /// A transient systemd user scope, for its own cgroup
+    #[serde(rename = "systemd-run")]
+    SystemdRun,
+    /// A shell spawned by raise itself
+    #[serde(rename = "direct")]
+    Direct,
+}
+
+impl FromStr for Launcher {
+    type Err = String;
+
+    fn from_str(launcher: &str) -> Result<Self, Self::Err> {
+        match launcher {
+            "hyprctl" => Ok(Launcher::Hyprctl),
+            "systemd-run" => Ok(Launcher::SystemdRun),
+            "direct" => Ok(Launcher::Direct),
+            _ => Err(format!(
+                "unknown launcher `{launcher}`, expected `hyprctl`, `systemd-run` or `direct`"
+            )),
+        }
+    }
+}
+
+impl Launcher {
+    /// The program and arguments that run a shell command, or `None` when Hyprland runs it
+    pub fn argv(self, command: &str) -> Result<Option<Vec<String>>> {
+        let shell = ["sh", "-c", command];
+        let argv = match self {
+            Launcher::Hyprctl => return Ok(None),
+            Launcher::SystemdRun => ["systemd-run", "--user", "--scope", "--"]
+                .into_iter()
+                .chain(shell)
+                .collect(),
+            Launcher::Direct => shell.to_vec(),
+        };
+        if find_in_path(argv[0]).is_none() {
+            let message = format!("the launcher needs `{}`, which is not in PATH", argv[0]);
+            return Err(RaiseError::InvalidArgs(message));
+        }
+        Ok(Some(argv.into_iter().map(String::from).collect()))
+    }
+}
+
+/// Where a program would be run from
+pub(crate) fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+impl Spawn<'_> {
+    pub fn spawn(&self) -> Result<()> {
+        let Some((program, arguments)) = self.argv.split_first() else {
+            return Err(RaiseError::InvalidArgs(
+                "the command to launch is empty".into(),
+            ));
+        };
+        let mut command = Command::new(program);
+        command.args(arguments);
+        if self.detach {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            // A new session has no controlling terminal, so the program outlives the one
+            // raise was started from. setsid is safe to call between fork and exec.
+            #[cfg(unix)]
+            unsafe {
+                std::os::unix::process::CommandExt::pre_exec(&mut command, || {
+                    nix::unistd::setsid()
+                        .map(drop)
+                        .map_err(std::io::Error::from)
+                });
+            }
+        }
+        if let Some(log) = self.log {
+            command.stdout(log.try_clone()?).stderr(log.try_clone()?);
+        }
+        if let Some(cwd) = self.cwd {
+            command.current_dir(cwd);
+        }
+        command.spawn().map_err(|source| RaiseError::LaunchFailed {
+            command: self.argv.join(" "),
+            source,
+        })?;
+        Ok(())
+    }
+}
+
+/// Quote a word for `sh`, so that it stays one word whatever it contains
+pub fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// A shell command that runs `command` in `dir`, for commands run by Hyprland, which
+/// always starts them in its own working directory
+pub fn in_dir(command: &str, dir: &Path) -> String {
+    format!("cd {} && {command}", shell_quote(&dir.to_string_lossy()))
+}
+
+/// Replace `{name}` placeholders with their values, with `{{` and `}}` for literal braces
+pub fn substitute(template: &str, values: &[(&str, Option<&str>)]) -> Result<String> {
+    let invalid = |reason: String| RaiseError::InvalidArgs(format!("in `{template}`: {reason}"));
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(index) = rest.find(['{', '}']) {
+        output.push_str(&rest[..index]);
+        let brace = &rest[index..];
+        if brace.starts_with("{{") || brace.starts_with("}}") {
+            output.push_str(&brace[..1]);
+            rest = &brace[2..];
+        } else if brace.starts_with('}') {
+            return Err(invalid(
+                "unmatched `}`, write `}}` for a literal brace".into(),
+            ));
+        } else {
+            let end = brace
+                .find('}')
+                .ok_or_else(|| invalid("unclosed `{`".into()))?;
+            let name = &brace[1..end];
+            match values.iter().find(|(key, _)| *key == name) {
+                Some((_, Some(value))) => output.push_str(value),
+                Some((_, None)) => return Err(invalid(format!("`{{{name}}}` has no value"))),
+                None => return Err(invalid(format!("unknown placeholder `{{{name}}}`"))),
+            }
+            rest = &brace[end + 1..];
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// The session of a program spawned like this, which it reports through a log file
+    fn session_of(detach: bool) -> i32 {
+        let path = std::env::temp_dir().join(format!("raise-sid-{}-{detach}", std::process::id()));
+        let log = File::create(&path).unwrap();
+        // The sixth field of stat is the session id
+        let argv = ["sh", "-c", "cut -d ' ' -f 6 /proc/self/stat"].map(String::from);
+        let spawn = Spawn {
+            argv: &argv,
+            detach,
+            log: Some(&log),
+            cwd: None,
+        };
+        spawn.spawn().unwrap();
+        let start = Instant::now();
+        let session = loop {
+            let output = std::fs::read_to_string(&path).unwrap();
+            if output.ends_with('\n') {
+                break output.trim().parse().unwrap();
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "the program did not report"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        std::fs::remove_file(path).unwrap();
+        session
+    }
+
+    #[test]
+    fn detached_programs_lead_their_own_session() {
+        let own = nix::unistd::getsid(None).unwrap().as_raw();
+        assert_eq!(session_of(false), own);
+        assert_ne!(session_of(true), own);
+    }
+}
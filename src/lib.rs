@@ -0,0 +1,33 @@
+//! Run or raise for Hyprland.
+
+#[cfg(all(unix, feature = "bench"))]
+pub mod bench;
+pub mod cache;
+mod client;
+pub mod color;
+pub mod config;
+pub mod debounce;
+pub mod doctor;
+mod error;
+#[cfg(unix)]
+pub mod events;
+pub mod filter;
+pub mod glob;
+pub mod hyprctl;
+pub mod keys;
+pub mod launch;
+pub mod list;
+pub mod menu;
+pub mod minimize;
+pub mod order;
+pub mod peek;
+pub mod plan;
+pub mod policy;
+pub mod process;
+pub mod urgent;
+pub mod version;
+
+pub use client::{Client, Fullscreen, Workspace};
+pub use error::{RaiseError, Result};
+pub use filter::{list_clients, ClientFilter};
+pub use launch::Launch;
@@ -0,0 +1,143 @@
+use crate::Client;
+use miniserde::json;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// How `--list` prints windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// Aligned columns, for people
+    Table,
+    /// One JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    /// Only addresses, one per line
+    Plain,
+}
+
+impl FromStr for ListFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "table" => Ok(ListFormat::Table),
+            "json" => Ok(ListFormat::Json),
+            "jsonl" => Ok(ListFormat::Jsonl),
+            "plain" => Ok(ListFormat::Plain),
+            _ => Err(format!(
+                "unknown format `{format}`, expected `table`, `json`, `jsonl` or `plain`"
+            )),
+        }
+    }
+}
+
+impl ListFormat {
+    /// A table on a terminal, and lines a program can read one at a time otherwise
+    pub fn detect() -> Self {
+        if std::io::stdout().is_terminal() {
+            ListFormat::Table
+        } else {
+            ListFormat::Jsonl
+        }
+    }
+
+    /// The clients in this format, as lines that each end in a newline
+    pub fn render(self, clients: &[&Client]) -> String {
+        let lines: Vec<String> = match self {
+            ListFormat::Table => table(clients),
+            ListFormat::Json => {
+                let clients = clients
+                    .iter()
+                    .map(|client| (*client).clone())
+                    .collect::<Vec<_>>();
+                vec![json::to_string(&clients)]
+            }
+            ListFormat::Jsonl => clients
+                .iter()
+                .map(|client| json::to_string(*client))
+                .collect(),
+            ListFormat::Plain => clients
+                .iter()
+                .map(|client| client.address.clone())
+                .collect(),
+        };
+        lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+}
+
+fn table(clients: &[&Client]) -> Vec<String> {
+    let header = ["ADDRESS", "CLASS", "WORKSPACE", "TITLE"];
+    let rows = clients.iter().map(|client| {
+        let Client {
+            address,
+            class,
+            title,
+            workspace,
+            ..
+        } = client;
+        [
+            address.as_str(),
+            class.as_str(),
+            workspace.name.as_str(),
+            title.as_str(),
+        ]
+    });
+    let rows = std::iter::once(header).chain(rows).collect::<Vec<_>>();
+    // Titles go last, where they can be as long as they are
+    let widths = [0, 1, 2].map(|column| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or_default()
+    });
+    let [address_width, class_width, workspace_width] = widths;
+    rows.iter()
+        .map(|[address, class, workspace, title]| {
+            let line = format!(
+                "{address:<address_width$}  {class:<class_width$}  \
+                 {workspace:<workspace_width$}  {title}"
+            );
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(class: &str, address: &str) -> Client {
+        let json = format!(
+            r#"{{"class": "{class}", "title": "", "address": "{address}", "pid": 0, "monitor": 0,
+                "workspace": {{"id": 1, "name": "1"}}, "pinned": false, "xwayland": false,
+                "floating": false}}"#
+        );
+        json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn list_formats() {
+        let (kitty, firefox) = (client("kitty", "0x1"), client("firefox", "0x20"));
+        let firefox = Client {
+            title: "Mozilla Firefox".into(),
+            ..firefox
+        };
+        let clients = [&kitty, &firefox];
+        assert_eq!(
+            ListFormat::Table.render(&clients),
+            "ADDRESS  CLASS    WORKSPACE  TITLE\n\
+             0x1      kitty    1\n\
+             0x20     firefox  1          Mozilla Firefox\n"
+        );
+        assert_eq!(ListFormat::Plain.render(&clients), "0x1\n0x20\n");
+        let lines = ListFormat::Jsonl.render(&clients);
+        assert_eq!(lines.lines().count(), 2);
+        let array = ListFormat::Json.render(&clients);
+        assert_eq!(
+            array,
+            format!("[{}]\n", lines.trim_end().replace('\n', ","))
+        );
+        assert_eq!(ListFormat::Json.render(&[]), "[]\n");
+    }
+}
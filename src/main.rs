@@ -1,99 +1,4256 @@
-use anyhow::{Context, Result, bail};
+use anyhow::{bail, Context, Result};
 use argh::FromArgs;
-use miniserde::{json, Deserialize};
-use std::process::{Child, Command};
+use miniserde::json::{self, Value};
+use raise::color::{Color, ColorChoice};
+use raise::config::{ConfigFile, Layer, RaiseConfig};
+#[cfg(unix)]
+use raise::events::{Action, Event};
+use raise::filter::{ClientFilter, JsonMatch, Pattern};
+use raise::hyprctl::{
+    self, AllInstances, BestEffort, Cached, ClassMap, Compositor, FocusMethod, Hyprctl,
+};
+use raise::keys::{Keys, KeysTool};
+use raise::launch::{self, shell_quote, LaunchCommand, Launcher, Spawn};
+use raise::list::ListFormat;
+use raise::minimize;
+use raise::order::Order;
+use raise::peek::{self, Peeked};
+use raise::plan::{Op, Planner};
+use raise::policy::{OnMany, OnNone, OnOne};
+use raise::{cache, debounce, glob::Glob, urgent, version, Client, Launch, RaiseError};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+#[cfg(unix)]
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long --after-launch-focus waits when --wait is not given
+const DEFAULT_WAIT: u64 = 5000;
 
 #[derive(FromArgs)]
 /// Raise window if it exists, otherwise launch new window.
+#[argh(
+    note = "Run `raise doctor` to check that raise can find and talk to Hyprland, and \
+               `raise watch` to act on windows as they open."
+)]
 struct Args {
     /// class to focus
     #[argh(option, short = 'c')]
-    class: String,
+    class: Option<String>,
+
+    /// compare classes after Unicode NFC normalization, for classes that are spelled with
+    /// composed characters on one side and decomposed ones on the other
+    #[argh(switch)]
+    normalize: bool,
+
+    /// turn --normalize off where a profile or $RAISE_NORMALIZE turns it on
+    #[argh(switch)]
+    no_normalize: bool,
+
+    /// also match windows whose class was --class when they were created, like Electron
+    /// applications that change their class once loaded
+    #[argh(switch)]
+    class_or_initial: bool,
+
+    /// turn --class-or-initial off where a profile or $RAISE_CLASS_OR_INITIAL turns it on
+    #[argh(switch)]
+    no_class_or_initial: bool,
+
+    /// treat windows of class `reported` as class `canonical`, given as
+    /// `reported=canonical`, for applications whose class differs between versions or
+    /// builds; adds to the `class_map` of the configuration file, can be repeated
+    #[argh(option)]
+    map_class: Vec<String>,
+
+    /// match windows without a class, which some native Wayland applications have
+    #[argh(switch)]
+    classless: bool,
+
+    /// turn --classless off where a profile or $RAISE_CLASSLESS turns it on
+    #[argh(switch)]
+    no_classless: bool,
 
-    /// command to launch
+    /// match the class of the focused window, to cycle through windows of the same
+    /// application
+    #[argh(switch)]
+    current_class: bool,
+
+    /// turn --current-class off where a profile or $RAISE_CURRENT_CLASS turns it on
+    #[argh(switch)]
+    no_current_class: bool,
+
+    /// title substring to focus
+    #[argh(option, short = 't')]
+    title: Option<String>,
+
+    /// only match XWayland windows with `yes`, or only native ones with `no`
+    #[argh(option, from_str_fn(yes_no))]
+    xwayland: Option<bool>,
+
+    /// only match floating windows with `yes`, or only tiled ones with `no`
+    #[argh(option, from_str_fn(yes_no))]
+    floating: Option<bool>,
+
+    /// only match windows whose `hyprctl clients -j` field equals a value, like
+    /// `floating=true` or `workspace.name=web`; can be repeated
+    #[argh(option)]
+    match_json: Vec<String>,
+
+    /// command to launch, where `{{class}}` and `{{title}}` are replaced by their options,
+    /// quoted for the shell
     #[argh(option, short = 'e')]
-    launch: String,
+    launch: Option<String>,
+
+    /// program and arguments to spawn directly instead of --launch, given after `--`
+    #[argh(positional)]
+    command: Vec<String>,
+
+    /// keep a directly spawned program running independently of raise
+    #[argh(switch)]
+    detach: bool,
+
+    /// turn --detach off where a profile or $RAISE_DETACH turns it on
+    #[argh(switch)]
+    no_detach: bool,
+
+    /// what starts --launch commands: `hyprctl` (default), `systemd-run` for a transient
+    /// user scope, or `direct` to spawn a shell from raise
+    #[argh(option)]
+    launcher: Option<Launcher>,
+
+    /// append the output of programs raise spawns itself to this file, which is where
+    /// --launcher direct and commands after `--` end up
+    #[argh(option)]
+    log_launch: Option<String>,
+
+    /// directory to launch in, for --launch as well as commands after `--`
+    #[argh(option)]
+    cwd: Option<String>,
+
+    /// only match windows on workspaces whose name matches this glob, or this regex when
+    /// it starts with `re:`
+    #[argh(option)]
+    workspace_pattern: Option<String>,
+
+    /// only match windows on special workspaces, such as scratchpads, and move them to the
+    /// current workspace before focusing; works on its own or with other filters
+    #[argh(switch)]
+    only_special: bool,
+
+    /// turn --only-special off where a profile or $RAISE_ONLY_SPECIAL turns it on
+    #[argh(switch)]
+    no_only_special: bool,
+
+    /// skip windows hidden because another window swallowed them
+    #[argh(switch)]
+    skip_swallowed: bool,
+
+    /// turn --skip-swallowed off where a profile or $RAISE_SKIP_SWALLOWED turns it on
+    #[argh(switch)]
+    no_skip_swallowed: bool,
+
+    /// reuse clients queried from the same instance by a previous invocation within this
+    /// many milliseconds
+    #[argh(option)]
+    cache: Option<u64>,
+
+    /// do nothing when the last invocation selecting the same windows acted less than this
+    /// many milliseconds ago, so a double tap does not cycle away; 0 (default) never does
+    #[argh(option)]
+    debounce: Option<u64>,
+
+    /// directory for what raise keeps between invocations, such as --cache, --debounce
+    /// and --peek state, $XDG_RUNTIME_DIR by default
+    #[argh(option)]
+    state_dir: Option<String>,
+
+    /// toggle the named special workspace, launching into it first if class is not running
+    #[argh(option)]
+    toggle_special: Option<String>,
+
+    /// launch into the named special workspace without showing it or taking focus, unless
+    /// a matching window exists; --toggle-special with the same name shows it later
+    #[argh(option)]
+    launch_hidden: Option<String>,
+
+    /// act on the window with this address instead of matching by class
+    #[argh(option)]
+    address: Option<String>,
+
+    /// do not check that the --address window exists
+    #[argh(switch)]
+    no_verify: bool,
+
+    /// move the window to the current workspace before focusing it
+    #[argh(switch)]
+    move_to_current: bool,
+
+    /// turn --move-to-current off where a profile or $RAISE_MOVE_TO_CURRENT turns it on
+    #[argh(switch)]
+    no_move_to_current: bool,
+
+    /// move every matching window to the current workspace, then focus one as usual
+    #[argh(switch)]
+    gather: bool,
+
+    /// turn --gather off where a profile or $RAISE_GATHER turns it on
+    #[argh(switch)]
+    no_gather: bool,
+
+    /// with --gather, focus the window that was focused before instead of a gathered
+    /// one; with --silent-move as well, focus does not change at all
+    #[argh(switch)]
+    keep_focus: bool,
+
+    /// turn --keep-focus off where a profile or $RAISE_KEEP_FOCUS turns it on
+    #[argh(switch)]
+    no_keep_focus: bool,
+
+    /// move the window this many workspaces over before focusing it, like `+1` or `-2`
+    #[argh(option, from_str_fn(offset))]
+    move_relative: Option<i64>,
+
+    /// move the window to the nearest empty workspace before focusing it
+    #[argh(switch)]
+    move_to_nearest_empty: bool,
+
+    /// turn --move-to-nearest-empty off where a profile or $RAISE_MOVE_TO_NEAREST_EMPTY turns it on
+    #[argh(switch)]
+    no_move_to_nearest_empty: bool,
+
+    /// only move launched windows, once they appear, and focus existing windows where
+    /// they are; waits for --wait milliseconds, 5000 if unset
+    #[argh(switch)]
+    on_launch_only_move: bool,
+
+    /// turn --on-launch-only-move off where a profile or $RAISE_ON_LAUNCH_ONLY_MOVE turns it on
+    #[argh(switch)]
+    no_on_launch_only_move: bool,
+
+    /// with --move-to-nearest-empty, only use empty workspaces on the current monitor
+    #[argh(switch)]
+    empty_on_monitor: bool,
+
+    /// turn --empty-on-monitor off where a profile or $RAISE_EMPTY_ON_MONITOR turns it on
+    #[argh(switch)]
+    no_empty_on_monitor: bool,
+
+    /// make a fullscreen window fullscreen again once it is moved and focused; it leaves
+    /// fullscreen for the move either way
+    #[argh(switch)]
+    restore_fullscreen: bool,
+
+    /// turn --restore-fullscreen off where a profile or $RAISE_RESTORE_FULLSCREEN turns it on
+    #[argh(switch)]
+    no_restore_fullscreen: bool,
+
+    /// move without shifting focus, leaving focusing to the explicit focus dispatch
+    #[argh(switch)]
+    silent_move: bool,
+
+    /// turn --silent-move off where a profile or $RAISE_SILENT_MOVE turns it on
+    #[argh(switch)]
+    no_silent_move: bool,
+
+    /// dispatcher for moving windows, `movetoworkspace` (default) or `movetoworkspacesilent`
+    #[argh(option)]
+    move_dispatcher: Option<String>,
+
+    /// after launching, wait up to this many milliseconds for the new window to appear
+    #[argh(option)]
+    wait: Option<u64>,
+
+    /// focus the launched window once it appears, even if window rules say otherwise
+    #[argh(switch)]
+    after_launch_focus: bool,
+
+    /// turn --after-launch-focus off where a profile or $RAISE_AFTER_LAUNCH_FOCUS turns it on
+    #[argh(switch)]
+    no_after_launch_focus: bool,
+
+    /// after launching, wait for and focus a new window of this class, ignoring windows
+    /// of other classes like splash screens; waits for --wait milliseconds, 5000 if unset
+    #[argh(option)]
+    wait_for: Option<String>,
+
+    /// fail when no matching window appears after launching, within --wait milliseconds
+    /// or 5000 if unset
+    #[argh(switch)]
+    require_launch: bool,
+
+    /// turn --require-launch off where a profile or $RAISE_REQUIRE_LAUNCH turns it on
+    #[argh(switch)]
+    no_require_launch: bool,
+
+    /// like --require-launch, but also focus the launched window and fail unless it is
+    /// focused within the same time, for scripts that type into it next
+    #[argh(switch)]
+    launch_and_wait_for_focus: bool,
+
+    /// turn --launch-and-wait-for-focus off where a profile or $RAISE_LAUNCH_AND_WAIT_FOR_FOCUS turns it on
+    #[argh(switch)]
+    no_launch_and_wait_for_focus: bool,
+
+    /// bring every matching window to the front, without moving them, before focusing one
+    #[argh(switch)]
+    raise_all: bool,
+
+    /// turn --raise-all off where a profile or $RAISE_RAISE_ALL turns it on
+    #[argh(switch)]
+    no_raise_all: bool,
+
+    /// send the dispatches for a window one by one instead of as one `hyprctl --batch`
+    #[argh(switch)]
+    no_batch: bool,
+
+    /// batch dispatches again where a profile or $RAISE_NO_BATCH sets --no-batch
+    #[argh(switch)]
+    batch: bool,
+
+    /// carry on when hyprctl rejects a dispatch, reporting it only with `--verbose`; by
+    /// default the first failed dispatch stops raise with an error
+    #[argh(switch, long = "best-effort")]
+    best_effort: bool,
+
+    /// turn --best-effort off where a profile or $RAISE_BEST_EFFORT turns it on
+    #[argh(switch)]
+    no_best_effort: bool,
+
+    /// how to focus: `plain` (default) focuses, `raise` also brings the window to the front
+    #[argh(option)]
+    focus_method: Option<FocusMethod>,
+
+    /// move the cursor to the middle of the window after focusing it
+    #[argh(switch)]
+    cursor_center: bool,
+
+    /// turn --cursor-center off where a profile or $RAISE_CURSOR_CENTER turns it on
+    #[argh(switch)]
+    no_cursor_center: bool,
+
+    /// float and center the window for a look, putting it back as it was laid out on the
+    /// next --peek for it
+    #[argh(switch)]
+    peek: bool,
+
+    /// turn --peek off where a profile or $RAISE_PEEK turns it on
+    #[argh(switch)]
+    no_peek: bool,
+
+    /// milliseconds after which a window --peek floated goes back, by the next invocation
+    /// or `raise watch`
+    #[argh(option)]
+    peek_timeout: Option<u64>,
+
+    /// read the active window back after focusing and fail if focus went elsewhere, such
+    /// as to a modal dialog, focusing once more before giving up
+    #[argh(switch)]
+    verify_focus: bool,
+
+    /// turn --verify-focus off where a profile or $RAISE_VERIFY_FOCUS turns it on
+    #[argh(switch)]
+    no_verify_focus: bool,
+
+    /// focus the monitor of the window before focusing the window itself
+    #[argh(switch)]
+    focus_monitor: bool,
+
+    /// turn --focus-monitor off where a profile or $RAISE_FOCUS_MONITOR turns it on
+    #[argh(switch)]
+    no_focus_monitor: bool,
+
+    /// focus the nth matching window (starting at 1), counting in --order
+    #[argh(option)]
+    index: Option<usize>,
+
+    /// order to cycle through matching windows in, which --index counts in too: `list`
+    /// (default) as `hyprctl clients` lists them, `reverse`, `address`, `workspace`, `mru`
+    /// for most recently focused first, or `shuffle`
+    #[argh(option)]
+    order: Option<Order>,
+
+    /// seed for --order shuffle, to get the same order every time
+    #[argh(option)]
+    seed: Option<u64>,
+
+    /// focus the most recently created matching window, like one that was just launched
+    #[argh(switch)]
+    newest: bool,
+
+    /// turn --newest off where a profile or $RAISE_NEWEST turns it on
+    #[argh(switch)]
+    no_newest: bool,
+
+    /// when several windows match, pick one with a dmenu-style menu instead of cycling,
+    /// which is `wofi --dmenu` or `rofi -dmenu`; cancelling the menu does nothing
+    #[argh(switch)]
+    select_menu: bool,
+
+    /// turn --select-menu off where a profile or $RAISE_SELECT_MENU turns it on
+    #[argh(switch)]
+    no_select_menu: bool,
+
+    /// the menu program for --select-menu, implying it, which gets one line per window
+    /// on stdin and prints the chosen one
+    #[argh(option)]
+    menu: Option<String>,
+
+    /// when a matching window is focused, go back to the previously focused window
+    #[argh(switch)]
+    toggle_last: bool,
+
+    /// turn --toggle-last off where a profile or $RAISE_TOGGLE_LAST turns it on
+    #[argh(switch)]
+    no_toggle_last: bool,
+
+    /// when a matching window is focused, minimize it to a hidden special workspace; it
+    /// is restored to the workspace it was on when raised again
+    #[argh(switch)]
+    raise_or_minimize: bool,
+
+    /// turn --raise-or-minimize off where a profile or $RAISE_RAISE_OR_MINIMIZE turns it on
+    #[argh(switch)]
+    no_raise_or_minimize: bool,
+
+    /// focus a matching window that asks for attention, if one other than the focused
+    /// window does, before cycling as usual; `raise watch --track-urgent` has to be
+    /// running to know which do
+    #[argh(switch)]
+    urgent_first: bool,
+
+    /// turn --urgent-first off where a profile or $RAISE_URGENT_FIRST turns it on
+    #[argh(switch)]
+    no_urgent_first: bool,
+
+    /// never launch, and fail when --index is out of range
+    #[argh(switch)]
+    no_launch: bool,
+
+    /// launch again where a profile or $RAISE_NO_LAUNCH sets --no-launch
+    #[argh(switch)]
+    allow_launch: bool,
+
+    /// launch even when a matching window exists
+    #[argh(switch)]
+    force_launch: bool,
+
+    /// turn --force-launch off where a profile or $RAISE_FORCE_LAUNCH turns it on
+    #[argh(switch)]
+    no_force_launch: bool,
+
+    /// when no window matches: `launch` or do `nothing`, like --no-launch
+    #[argh(option)]
+    on_none: Option<OnNone>,
+
+    /// when one window matches: `focus` it, or `toggle` back to the previous window
+    /// when it is focused, like --toggle-last
+    #[argh(option)]
+    on_one: Option<OnOne>,
+
+    /// when several windows match: `cycle` through them, pick one from a `menu` like
+    /// --select-menu, or `gather` them like --gather
+    #[argh(option)]
+    on_many: Option<OnMany>,
+
+    /// once the window raise focused has focus, send it keys like `ctrl+l` or
+    /// `ctrl+shift+t`, where the key is an XKB name such as `l`, `Return` or `F5`
+    #[argh(option)]
+    send_keys: Option<String>,
+
+    /// what sends --send-keys: `hyprctl` (default) through `sendshortcut`, or `wtype`
+    #[argh(option)]
+    keys_tool: Option<KeysTool>,
+
+    /// once the window raise focused has focus, and keys are sent, switch to this
+    /// Hyprland submap, for keybinds that only apply to that application
+    #[argh(option)]
+    enter_submap: Option<String>,
+
+    /// like --enter-submap, but return to the default submap
+    #[argh(switch)]
+    reset_submap: bool,
+
+    /// turn --reset-submap off where a profile or $RAISE_RESET_SUBMAP turns it on
+    #[argh(switch)]
+    no_reset_submap: bool,
+
+    /// focus a matching window that is in a group before launching, so Hyprland opens the
+    /// new window into that group; locked groups take no new windows
+    #[argh(switch)]
+    join_group: bool,
+
+    /// turn --join-group off where a profile or $RAISE_JOIN_GROUP turns it on
+    #[argh(switch)]
+    no_join_group: bool,
+
+    /// close the focused matching window, or the first one, instead of focusing it
+    #[argh(switch)]
+    close: bool,
+
+    /// turn --close off where a profile or $RAISE_CLOSE turns it on
+    #[argh(switch)]
+    no_close: bool,
+
+    /// close like --close, then kill the process of the window with SIGKILL if it is
+    /// still open after this many milliseconds
+    #[argh(option)]
+    grace_close: Option<u64>,
+
+    /// print how many windows match and exit without acting
+    #[argh(switch)]
+    count: bool,
+
+    /// print the matching windows and exit without acting, in the order they cycle in
+    #[argh(switch)]
+    list: bool,
+
+    /// how --list prints windows: `table`, `json` for an array, `jsonl` for an object
+    /// per line, or `plain` for addresses; a table on a terminal, `jsonl` otherwise
+    #[argh(option)]
+    format: Option<ListFormat>,
+
+    /// print a sentence on what raise would do and why, without doing it; --verbose adds
+    /// the list of candidates
+    #[argh(switch)]
+    explain: bool,
+
+    /// print what raise would do as a JSON array of operations like
+    /// `{{"op": "focus", "address": "0x1"}}`, without doing it
+    #[argh(switch)]
+    plan: bool,
+
+    /// match windows in every running Hyprland instance, like on a multi-seat machine, and
+    /// act in the instance the window is in; windows of $HYPRLAND_INSTANCE_SIGNATURE come
+    /// first, and launching happens there
+    #[argh(switch)]
+    all_instances: bool,
+
+    /// turn --all-instances off where a profile or $RAISE_ALL_INSTANCES turns it on
+    #[argh(switch)]
+    no_all_instances: bool,
+
+    /// launch even when no Hyprland instance is found, instead of failing
+    #[argh(switch)]
+    launch_on_no_compositor: bool,
+
+    /// turn --launch-on-no-compositor off where a profile or $RAISE_LAUNCH_ON_NO_COMPOSITOR turns it on
+    #[argh(switch)]
+    no_launch_on_no_compositor: bool,
+
+    /// fail when the windows cannot be listed, instead of launching without knowing
+    /// whether a matching window is open
+    #[argh(switch)]
+    strict: bool,
+
+    /// turn --strict off where a profile or $RAISE_STRICT turns it on
+    #[argh(switch)]
+    no_strict: bool,
+
+    /// print the time spent in each phase to stderr
+    #[argh(switch)]
+    timings: bool,
+
+    /// turn --timings off where a profile or $RAISE_TIMINGS turns it on
+    #[argh(switch)]
+    no_timings: bool,
+
+    /// configuration profile to start from, overridden by RAISE_* variables and options
+    #[argh(option, short = 'p')]
+    profile: Option<String>,
+
+    /// configuration file, instead of $RAISE_CONFIG or ~/.config/raise/config.json
+    #[argh(option)]
+    config: Option<PathBuf>,
+
+    /// print the effective configuration as JSON and exit
+    #[argh(switch)]
+    show_config: bool,
+
+    /// print the detected Hyprland version and exit
+    #[argh(switch)]
+    show_version_info: bool,
+
+    /// print diagnostics to stderr
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+
+    /// turn --verbose off where a profile or $RAISE_VERBOSE turns it on
+    #[argh(switch)]
+    no_verbose: bool,
+
+    /// color diagnostics and errors: `auto` (default) on a terminal without NO_COLOR,
+    /// `always` or `never`
+    #[argh(option)]
+    color: Option<ColorChoice>,
+}
+
+/// Check that raise can find and talk to Hyprland.
+#[derive(FromArgs)]
+struct Doctor {}
+
+/// Act on windows of a class whenever Hyprland announces them, until it exits.
+#[cfg(unix)]
+#[derive(FromArgs)]
+#[argh(
+    note = "Actions are `focus`, `float` and `tile`. `openwindow`, `urgent` and \
+               `activewindow` events are acted on; `--on-active` acts on the window that \
+               was focused, so `focus` does nothing there. Windows of any class are \
+               tracked by `--track-urgent` until they are focused or closed."
+)]
+struct Watch {
+    /// class of the windows to act on, which the actions require
+    #[argh(option, short = 'c')]
+    class: Option<String>,
+
+    /// action when a window of the class opens
+    #[argh(option)]
+    on_open: Option<Action>,
+
+    /// action when a window of the class asks for attention
+    #[argh(option)]
+    on_urgent: Option<Action>,
+
+    /// action when a window of the class gets focused
+    #[argh(option)]
+    on_active: Option<Action>,
+
+    /// note down which windows ask for attention, for `raise --urgent-first` in the same
+    /// state directory
+    #[argh(switch)]
+    track_urgent: bool,
+
+    /// directory to note urgent windows down in, $XDG_RUNTIME_DIR by default
+    #[argh(option)]
+    state_dir: Option<String>,
+
+    /// print every action, and dispatches that failed, to stderr
+    #[argh(switch, short = 'v')]
+    verbose: bool,
 }
 
-#[derive(Deserialize, Debug)]
-struct Client {
-    class: String,
-    address: String,
+/// Time listing windows through hyprctl and through the socket directly.
+#[cfg(all(unix, feature = "bench"))]
+#[derive(FromArgs)]
+struct Bench {
+    /// how many times each backend lists the windows, 100 by default
+    #[argh(option, short = 'n', default = "100")]
+    iterations: usize,
 }
 
-fn launch_command(args: &Args) -> std::io::Result<Child> {
-    Command::new("hyprctl")
-        .arg("keyword")
-        .arg("exec")
-        .arg(&args.launch)
-        .spawn()
+impl Args {
+    /// Options given on the command line, as the topmost configuration layer, where each
+    /// switch and its negation set the setting either way and neither leaves it alone
+    fn layer(&self) -> raise::Result<Layer> {
+        let mut contradicted = None;
+        let mut switch = |name: &'static str, on: bool, off: bool| match (on, off) {
+            (true, true) => {
+                contradicted.get_or_insert(name);
+                None
+            }
+            (on, off) => (on || off).then_some(on),
+        };
+        let layer = Layer {
+            class: self.class.clone().map(Some),
+            normalize: switch("normalize", self.normalize, self.no_normalize),
+            class_or_initial: switch(
+                "class_or_initial",
+                self.class_or_initial,
+                self.no_class_or_initial,
+            ),
+            map_class: (!self.map_class.is_empty()).then(|| self.map_class.clone()),
+            classless: switch("classless", self.classless, self.no_classless),
+            current_class: switch("current_class", self.current_class, self.no_current_class),
+            title: self.title.clone().map(Some),
+            xwayland: self.xwayland.map(Some),
+            floating: self.floating.map(Some),
+            match_json: (!self.match_json.is_empty()).then(|| self.match_json.clone()),
+            launch: self
+                .launch
+                .clone()
+                .map(|command| Some(LaunchCommand::Shell(command))),
+            detach: switch("detach", self.detach, self.no_detach),
+            launcher: self.launcher,
+            log_launch: self.log_launch.clone().map(Some),
+            cwd: self.cwd.clone().map(Some),
+            workspace_pattern: self.workspace_pattern.clone().map(Some),
+            only_special: switch("only_special", self.only_special, self.no_only_special),
+            skip_swallowed: switch(
+                "skip_swallowed",
+                self.skip_swallowed,
+                self.no_skip_swallowed,
+            ),
+            cache: self.cache.map(Some),
+            state_dir: self.state_dir.clone().map(Some),
+            debounce: self.debounce,
+            toggle_special: self.toggle_special.clone().map(Some),
+            launch_hidden: self.launch_hidden.clone().map(Some),
+            move_to_current: switch(
+                "move_to_current",
+                self.move_to_current,
+                self.no_move_to_current,
+            ),
+            gather: switch("gather", self.gather, self.no_gather),
+            keep_focus: switch("keep_focus", self.keep_focus, self.no_keep_focus),
+            move_relative: self.move_relative.map(Some),
+            move_to_nearest_empty: switch(
+                "move_to_nearest_empty",
+                self.move_to_nearest_empty,
+                self.no_move_to_nearest_empty,
+            ),
+            on_launch_only_move: switch(
+                "on_launch_only_move",
+                self.on_launch_only_move,
+                self.no_on_launch_only_move,
+            ),
+            empty_on_monitor: switch(
+                "empty_on_monitor",
+                self.empty_on_monitor,
+                self.no_empty_on_monitor,
+            ),
+            restore_fullscreen: switch(
+                "restore_fullscreen",
+                self.restore_fullscreen,
+                self.no_restore_fullscreen,
+            ),
+            silent_move: switch("silent_move", self.silent_move, self.no_silent_move),
+            move_dispatcher: self.move_dispatcher.clone().map(Some),
+            wait: self.wait.map(Some),
+            after_launch_focus: switch(
+                "after_launch_focus",
+                self.after_launch_focus,
+                self.no_after_launch_focus,
+            ),
+            wait_for: self.wait_for.clone().map(Some),
+            require_launch: switch(
+                "require_launch",
+                self.require_launch,
+                self.no_require_launch,
+            ),
+            launch_and_wait_for_focus: switch(
+                "launch_and_wait_for_focus",
+                self.launch_and_wait_for_focus,
+                self.no_launch_and_wait_for_focus,
+            ),
+            raise_all: switch("raise_all", self.raise_all, self.no_raise_all),
+            no_batch: switch("no_batch", self.no_batch, self.batch),
+            best_effort: switch("best_effort", self.best_effort, self.no_best_effort),
+            focus_method: self.focus_method,
+            cursor_center: switch("cursor_center", self.cursor_center, self.no_cursor_center),
+            peek: switch("peek", self.peek, self.no_peek),
+            peek_timeout: self.peek_timeout.map(Some),
+            verify_focus: switch("verify_focus", self.verify_focus, self.no_verify_focus),
+            focus_monitor: switch("focus_monitor", self.focus_monitor, self.no_focus_monitor),
+            index: self.index.map(Some),
+            order: self.order,
+            seed: self.seed.map(Some),
+            newest: switch("newest", self.newest, self.no_newest),
+            select_menu: switch("select_menu", self.select_menu, self.no_select_menu),
+            menu: self.menu.clone().map(Some),
+            toggle_last: switch("toggle_last", self.toggle_last, self.no_toggle_last),
+            raise_or_minimize: switch(
+                "raise_or_minimize",
+                self.raise_or_minimize,
+                self.no_raise_or_minimize,
+            ),
+            urgent_first: switch("urgent_first", self.urgent_first, self.no_urgent_first),
+            no_launch: switch("no_launch", self.no_launch, self.allow_launch),
+            force_launch: switch("force_launch", self.force_launch, self.no_force_launch),
+            on_none: self.on_none.map(Some),
+            on_one: self.on_one.map(Some),
+            on_many: self.on_many.map(Some),
+            send_keys: self.send_keys.clone().map(Some),
+            keys_tool: self.keys_tool,
+            enter_submap: self.enter_submap.clone().map(Some),
+            reset_submap: switch("reset_submap", self.reset_submap, self.no_reset_submap),
+            join_group: switch("join_group", self.join_group, self.no_join_group),
+            close: switch("close", self.close, self.no_close),
+            grace_close: self.grace_close.map(Some),
+            all_instances: switch("all_instances", self.all_instances, self.no_all_instances),
+            launch_on_no_compositor: switch(
+                "launch_on_no_compositor",
+                self.launch_on_no_compositor,
+                self.no_launch_on_no_compositor,
+            ),
+            strict: switch("strict", self.strict, self.no_strict),
+            timings: switch("timings", self.timings, self.no_timings),
+            verbose: switch("verbose", self.verbose, self.no_verbose),
+            color: self.color,
+        };
+        match contradicted {
+            Some(name) => {
+                let flag = name.replace('_', "-");
+                let message = format!("--{flag} and its negation are mutually exclusive");
+                Err(RaiseError::InvalidArgs(message))
+            }
+            None => Ok(layer),
+        }
+    }
 }
 
-fn focus_window(address: &str) -> std::io::Result<Child> {
-    Command::new("hyprctl")
-        .arg("dispatch")
-        .arg("focuswindow")
-        .arg(format!("address:{address}"))
-        .spawn()
+/// A relative workspace, where the sign is optional for moving forward
+fn offset(value: &str) -> Result<i64, String> {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(format!(
+            "expected an offset like `+1` or `-2`, got `{value}`"
+        ));
+    }
+    value
+        .parse()
+        .map_err(|_| format!("offset `{value}` is out of range"))
 }
 
-fn get_current_matching_window(class: &str) -> Result<Client> {
-    let output = Command::new("hyprctl")
-        .arg("activewindow")
-        .arg("-j")
-        .output()?;
-    let stdout = String::from_utf8(output.stdout)
-        .context("Reading `hyprctl currentwindow -j` to string failed")?;
-    let client = json::from_str::<Client>(&stdout)?;
-    if class == &client.class {
-        Ok(client)
-    } else {
-        bail!("Current window is not of same class")
+fn yes_no(value: &str) -> Result<bool, String> {
+    match value {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(format!("expected `yes` or `no`, got `{value}`")),
     }
 }
 
-fn main() -> Result<()> {
-    // Get arguments
-    let args: Args = argh::from_env();
+/// First Hyprland release with the `m` modifier for `empty` workspaces
+const EMPTY_ON_MONITOR: (u32, u32, u32) = (0, 35, 0);
 
-    // Launch hyprctl
-    let json = Command::new("hyprctl").arg("clients").arg("-j").output();
-    match json {
-        Ok(output) if output.status.success() => {
-            // Deserialize output
-            let stdout = String::from_utf8(output.stdout)
-                .context("Reading `hyprctl clients -j` to string failed")?;
-            let clients = json::from_str::<Vec<Client>>(&stdout)
-                .context("Failed to parse `hyprctl clients -j`")?;
-
-            // Filter matching clients
-            let candidates = clients
-                .iter()
-                .filter(|client| client.class == args.class)
-                .collect::<Vec<_>>();
-            
-            // Are we currently focusing a window of this class?
-            if let Ok(Client { address, .. }) = get_current_matching_window(&args.class) {
-                // Focus next window based on first
-                if let Some(index) = candidates.iter().position(|client| client.address == address) {
-                    if let Some(next_client) = candidates.iter().cycle().skip(index + 1).next() {
-                        focus_window(&next_client.address)?;
-                    }
-                }
+/// Reject moving options that contradict each other or need a newer Hyprland
+fn check_move(hypr: &impl Compositor, config: &RaiseConfig) -> raise::Result<()> {
+    let targets = [
+        ("--move-to-current", config.move_to_current),
+        ("--gather", config.gather),
+        ("--move-relative", config.move_relative.is_some()),
+        ("--move-to-nearest-empty", config.move_to_nearest_empty),
+    ];
+    let given = targets
+        .iter()
+        .filter(|(_, given)| *given)
+        .map(|(name, _)| *name);
+    let given = given.collect::<Vec<_>>();
+    if given.len() > 1 {
+        let message = format!("{} are mutually exclusive", given.join(" and "));
+        return Err(RaiseError::InvalidArgs(message));
+    }
+    if config.on_launch_only_move && move_target(config).is_none() {
+        let message = "--on-launch-only-move requires --move-to-current, --move-relative or \
+             --move-to-nearest-empty";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.keep_focus && !config.gather && config.on_many != Some(OnMany::Gather) {
+        return Err(RaiseError::InvalidArgs(
+            "--keep-focus requires --gather".into(),
+        ));
+    }
+    if config.empty_on_monitor && !config.move_to_nearest_empty {
+        let message = "--empty-on-monitor requires --move-to-nearest-empty";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.empty_on_monitor {
+        match hypr.version() {
+            Ok(version) if !version.is_at_least(EMPTY_ON_MONITOR) => {
+                let (major, minor, patch) = EMPTY_ON_MONITOR;
+                return Err(RaiseError::InvalidArgs(format!(
+                    "--empty-on-monitor requires Hyprland v{major}.{minor}.{patch} or newer, \
+                     found {}",
+                    version.tag
+                )));
+            }
+            Ok(_) => {}
+            Err(error) if config.verbose => {
+                eprintln!("warning: could not check for --empty-on-monitor support: {error}");
+            }
+            Err(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reject options that contradict one another, or miss what they need, before anything
+/// happens
+fn validate(config: &RaiseConfig, args: &Args) -> raise::Result<()> {
+    if let Some(list) = &config.launch {
+        list.validate()?;
+    }
+    if config.launch.is_some() && !args.command.is_empty() {
+        let message = "--launch and a command after `--` are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    let cwd = config.cwd.as_deref().map(Path::new);
+    if let Some(cwd) = cwd.filter(|cwd| !cwd.is_dir()) {
+        let message = format!("--cwd {} is not a directory", cwd.display());
+        return Err(RaiseError::InvalidArgs(message));
+    }
+    // A broken pattern is an error before anything happens, not once windows are looked at
+    if let Some(pattern) = &config.workspace_pattern {
+        workspace_pattern(pattern)?;
+    }
+    move_dispatcher(config)?;
+    // Cached clients do not say which instance they came from
+    if config.all_instances && config.cache.is_some() {
+        let message = "--all-instances and --cache are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.strict && config.launch_on_no_compositor {
+        let message = "--strict and --launch-on-no-compositor are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    // A known address skips matching entirely, and with it everything matching takes
+    if args.address.is_some() {
+        return Ok(());
+    }
+
+    let launches = config.launch.is_some() || !args.command.is_empty();
+    match config.class.as_deref() {
+        Some("") => {
+            let message = "--class is empty, use --classless to match windows without a class";
+            return Err(RaiseError::InvalidArgs(message.into()));
+        }
+        Some(_) if config.classless => {
+            let message = "--class and --classless are mutually exclusive";
+            return Err(RaiseError::InvalidArgs(message.into()));
+        }
+        Some(_) if config.current_class => {
+            let message = "--class and --current-class are mutually exclusive";
+            return Err(RaiseError::InvalidArgs(message.into()));
+        }
+        None if config.classless && config.current_class => {
+            let message = "--classless and --current-class are mutually exclusive";
+            return Err(RaiseError::InvalidArgs(message.into()));
+        }
+        None if !config.classless
+            && !config.current_class
+            && config.workspace_pattern.is_none()
+            && !config.only_special =>
+        {
+            let message = "one of `--class`, `--classless`, `--current-class`, \
+                           `--workspace-pattern` or `--only-special` is required, \
+                           see `raise --help`";
+            return Err(RaiseError::InvalidArgs(message.into()));
+        }
+        _ => {}
+    }
+    if config.wait_for.as_deref() == Some("") {
+        return Err(RaiseError::InvalidArgs("--wait-for is empty".into()));
+    }
+    if config.index == Some(0) {
+        return Err(RaiseError::InvalidArgs("--index starts at 1".into()));
+    }
+    if config.force_launch && config.no_launch {
+        let message = "--force-launch and --no-launch are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.force_launch && !launches {
+        let message = "--force-launch requires `--launch` or a command after `--`";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.index.is_some() && config.toggle_last {
+        let message = "--toggle-last and --index are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.raise_or_minimize && config.toggle_last {
+        let message = "--raise-or-minimize and --toggle-last are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.launch_hidden.is_some() && config.toggle_special.is_some() {
+        let message = "--launch-hidden and --toggle-special are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.newest && config.index.is_some() {
+        let message = "--newest and --index are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.on_none == Some(OnNone::Launch) && !launches {
+        let message = "--on-none launch requires `--launch` or a command after `--`";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.on_none == Some(OnNone::Launch) && config.no_launch {
+        let message = "--on-none launch and --no-launch are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    let policy = config.on_one.is_some() || config.on_many.is_some();
+    if policy && (config.index.is_some() || config.newest) {
+        let message = "--on-one and --on-many are mutually exclusive with --index and --newest";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.on_many == Some(OnMany::Gather) {
+        let moving = [
+            ("--move-to-current", config.move_to_current),
+            ("--move-relative", config.move_relative.is_some()),
+            ("--move-to-nearest-empty", config.move_to_nearest_empty),
+        ];
+        if let Some((name, _)) = moving.iter().find(|(_, given)| *given) {
+            let message = format!("--on-many gather and {name} are mutually exclusive");
+            return Err(RaiseError::InvalidArgs(message));
+        }
+    }
+    if args.count && args.list {
+        let message = "--count and --list are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if args.format.is_some() && !args.list {
+        return Err(RaiseError::InvalidArgs("--format requires --list".into()));
+    }
+    if config.peek_timeout.is_some() && !config.peek {
+        return Err(RaiseError::InvalidArgs(
+            "--peek-timeout requires --peek".into(),
+        ));
+    }
+    if config.enter_submap.as_deref() == Some("") {
+        return Err(RaiseError::InvalidArgs("--enter-submap is empty".into()));
+    }
+    if config.enter_submap.is_some() && config.reset_submap {
+        let message = "--enter-submap and --reset-submap are mutually exclusive";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    // The workspace rule needs Hyprland to run the command, whatever the launcher
+    let shell = matches!(config.launch, Some(LaunchCommand::Shell(_)));
+    let class = config.class.is_some() || config.current_class;
+    if config.toggle_special.is_some() && !(class && shell) {
+        let message = "--toggle-special requires both `--class` and `--launch`";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    if config.launch_hidden.is_some() && !shell {
+        let message = "--launch-hidden requires `--launch`";
+        return Err(RaiseError::InvalidArgs(message.into()));
+    }
+    Ok(())
+}
+
+/// The dispatcher used for moving, which only comes from an allowlist so configuration
+/// cannot inject arbitrary dispatches
+fn move_dispatcher(config: &RaiseConfig) -> raise::Result<&'static str> {
+    match config.move_dispatcher.as_deref() {
+        None if config.silent_move => Ok("movetoworkspacesilent"),
+        None => Ok("movetoworkspace"),
+        Some(name) if config.silent_move && name != "movetoworkspacesilent" => {
+            Err(RaiseError::InvalidArgs(format!(
+                "--silent-move contradicts --move-dispatcher {name}"
+            )))
+        }
+        Some(name) => hyprctl::MOVE_DISPATCHERS
+            .iter()
+            .find(|dispatcher| **dispatcher == name)
+            .copied()
+            .ok_or_else(|| {
+                let known = hyprctl::MOVE_DISPATCHERS.join(", ");
+                RaiseError::InvalidArgs(format!("unknown move dispatcher `{name}`, use {known}"))
+            }),
+    }
+}
+
+/// Where the moving options send a window, in the form the move dispatcher takes
+fn move_target(config: &RaiseConfig) -> Option<String> {
+    if config.move_to_nearest_empty {
+        Some(
+            if config.empty_on_monitor {
+                "emptym"
             } else {
-                // Focus first window, otherwise launch command
-                match candidates.first() {
-                    Some(Client { address, .. }) => focus_window(address)?,
-                    _ => launch_command(&args)?,
-                };
+                "empty"
+            }
+            .to_string(),
+        )
+    } else if let Some(offset) = config.move_relative {
+        Some(format!("{offset:+}"))
+    } else if config.move_to_current {
+        Some("+0".to_string())
+    } else {
+        None
+    }
+}
+
+/// Where a window to focus gets moved to first, if anywhere
+fn workspace(config: &RaiseConfig) -> Option<String> {
+    let target = if config.on_launch_only_move {
+        None
+    } else {
+        move_target(config)
+    };
+    // Windows summoned from a special workspace come to the current one by default
+    let summoned = config.gather || config.only_special;
+    target.or_else(|| summoned.then(|| "+0".to_string()))
+}
+
+fn fullscreen_mode(client: &Client) -> &'static str {
+    client.fullscreen.unwrap_or_default().mode()
+}
+
+/// The settings that --on-none, --on-one or --on-many amount to with this many candidates
+fn with_policy(config: &RaiseConfig, count: usize) -> RaiseConfig {
+    let mut config = config.clone();
+    match count {
+        0 => {
+            if let Some(on_none) = config.on_none {
+                config.no_launch = on_none == OnNone::Nothing;
+            }
+        }
+        1 => {
+            if let Some(on_one) = config.on_one {
+                config.toggle_last = on_one == OnOne::Toggle;
             }
         }
-        // If hyprctl fails, just launch it
         _ => {
-            launch_command(&args)?;
+            if let Some(on_many) = config.on_many {
+                config.select_menu = on_many == OnMany::Menu;
+                if on_many != OnMany::Menu {
+                    config.menu = None;
+                }
+                config.gather = on_many == OnMany::Gather;
+            }
         }
     }
+    config
+}
 
-    // Success
-    Ok(())
+/// Dispatches that prepare a window before it gets focused
+///
+/// The client is unknown when targeting an unverified --address, in which case actions
+/// that depend on its state are skipped.
+fn prepare_dispatches(
+    config: &RaiseConfig,
+    address: &str,
+    client: Option<&Client>,
+) -> raise::Result<Vec<(&'static str, String)>> {
+    let mut dispatches = Vec::new();
+    // Pinned windows are on every workspace already, and moving them unpins them
+    let pinned = client.is_some_and(|client| client.pinned);
+    let workspace = workspace(config);
+    let moved = workspace.is_some() && !pinned;
+    if let Some(workspace) = workspace.as_ref().filter(|_| moved) {
+        // Moving a fullscreen window leaves it half fullscreen, and `fullscreen` only
+        // applies to the focused window
+        if let Some(client) = client.filter(|client| client.is_fullscreen()) {
+            if config.verbose {
+                eprintln!("Leaving fullscreen on {address} before moving it");
+            }
+            dispatches.push(("focuswindow", format!("address:{address}")));
+            dispatches.push(("fullscreen", fullscreen_mode(client).into()));
+        }
+        dispatches.push((
+            move_dispatcher(config)?,
+            format!("{workspace},address:{address}"),
+        ));
+    } else if workspace.is_some() && config.verbose {
+        eprintln!("Not moving {address}, it is pinned");
+    }
+    if config.focus_monitor {
+        match client {
+            // Moving brings the window to the current monitor already
+            Some(_) if moved => {}
+            Some(client) => dispatches.push(("focusmonitor", client.monitor.to_string())),
+            None if config.verbose => {
+                eprintln!("Skipping --focus-monitor, monitor of {address} is unknown");
+            }
+            None => {}
+        }
+    }
+    Ok(dispatches)
+}
+
+/// Apply the requested actions to a window, ending with focusing it
+///
+/// Several dispatches go out as one batch so Hyprland applies them in a single pass,
+/// instead of showing every intermediate state.
+fn raise_window(
+    hypr: &impl Compositor,
+    config: &RaiseConfig,
+    address: &str,
+    client: Option<&Client>,
+) -> raise::Result<()> {
+    let mut dispatches = prepare_dispatches(config, address, client)?;
+    dispatches.extend(config.focus_method.dispatches(address));
+    let moved = workspace(config).is_some() && client.is_some_and(|client| !client.pinned);
+    let fullscreen = client.filter(|client| moved && client.is_fullscreen());
+    if let Some(client) = fullscreen.filter(|_| config.restore_fullscreen) {
+        dispatches.push(("fullscreen", fullscreen_mode(client).into()));
+    }
+    send(hypr, config, &dispatches)
+}
+
+/// The --workspace-pattern, a regex after `re:` and a glob otherwise
+fn workspace_pattern(pattern: &str) -> raise::Result<Pattern> {
+    match pattern.strip_prefix("re:") {
+        Some(regex) => Pattern::regex(regex),
+        None => Glob::new(pattern).map(Pattern::Glob),
+    }
+}
+
+/// What --debounce tells invocations apart by: everything that selects the windows, with
+/// the class as resolved, so binds for different windows never bounce one another
+fn debounce_key(config: &RaiseConfig, class: Option<&str>) -> String {
+    let flag = |set: bool| set.then(|| "true".to_string());
+    let parts = [
+        ("class", class.map(String::from)),
+        ("classless", flag(config.classless)),
+        ("title", config.title.clone()),
+        ("workspace", config.workspace_pattern.clone()),
+        (
+            "xwayland",
+            config.xwayland.map(|xwayland| xwayland.to_string()),
+        ),
+        (
+            "floating",
+            config.floating.map(|floating| floating.to_string()),
+        ),
+        ("special", flag(config.only_special)),
+    ];
+    let conditions = config
+        .match_json
+        .iter()
+        .map(|condition| ("match", Some(condition.clone())));
+    parts
+        .into_iter()
+        .chain(conditions)
+        .filter_map(|(name, value)| Some(format!("{name}={}", json::to_string(&value?))))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Where state kept between invocations goes, like what --peek and --debounce remember
+fn state_dir(config: &RaiseConfig) -> PathBuf {
+    config
+        .state_dir
+        .as_deref()
+        .map_or_else(cache::runtime_dir, PathBuf::from)
+}
+
+/// Dispatches putting back the windows whose --peek-timeout is up, leaving out those that
+/// closed in the meantime
+fn unpeek_expired(
+    hypr: &impl Compositor,
+    dir: &Path,
+) -> raise::Result<Vec<(&'static str, String)>> {
+    let expired = peek::take_expired(dir);
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+    let clients = hypr.clients()?;
+    Ok(expired
+        .iter()
+        .filter(|(address, _)| clients.iter().any(|client| client.address == **address))
+        .flat_map(|(address, peeked)| peeked.restore(address))
+        .collect())
+}
+
+/// Hide the window on the minimized special workspace for --raise-or-minimize, remembering
+/// the workspace it was on unless only planning
+fn minimize_window(
+    hypr: &impl Compositor,
+    config: &RaiseConfig,
+    client: &Client,
+    clients: &[Client],
+    remember: bool,
+) -> raise::Result<()> {
+    if remember {
+        let mut minimized = minimize::load(&state_dir(config));
+        // Windows closed since have nothing left to restore
+        minimized.retain(|address, _| clients.iter().any(|client| client.address == *address));
+        minimized.insert(client.address.clone(), client.workspace.clone());
+        minimize::store(&state_dir(config), &minimized);
+    }
+    let argument = format!("{},address:{}", minimize::WORKSPACE, client.address);
+    hypr.dispatch("movetoworkspacesilent", &argument)
+}
+
+/// The dispatch that moves a window --raise-or-minimize hid back to where it was minimized
+/// from. A window taken out of hiding some other way stays where it is.
+fn unminimize(
+    config: &RaiseConfig,
+    client: &Client,
+    forget: bool,
+) -> Option<(&'static str, String)> {
+    let mut minimized = minimize::load(&state_dir(config));
+    let origin = minimized.remove(&client.address)?;
+    if forget {
+        minimize::store(&state_dir(config), &minimized);
+    }
+    if client.workspace.name != minimize::WORKSPACE {
+        return None;
+    }
+    let argument = format!("{},address:{}", minimize::target(&origin), client.address);
+    Some(("movetoworkspacesilent", argument))
+}
+
+/// Send dispatches as one batch, or one by one with --no-batch
+fn send(
+    hypr: &impl Compositor,
+    config: &RaiseConfig,
+    dispatches: &[(&str, String)],
+) -> raise::Result<()> {
+    match dispatches {
+        [] => Ok(()),
+        [(dispatcher, argument)] => hypr.dispatch(dispatcher, argument),
+        _ if !config.no_batch => hypr.dispatch_batch(dispatches),
+        _ => dispatches
+            .iter()
+            .try_for_each(|(dispatcher, argument)| hypr.dispatch(dispatcher, argument)),
+    }
+}
+
+/// Move the matching windows other than the one to focus to the current workspace for
+/// --gather, leaving out pinned ones, which are there already
+fn gather(
+    hypr: &impl Compositor,
+    config: &RaiseConfig,
+    client: &Client,
+    candidates: &[&Client],
+) -> raise::Result<()> {
+    let dispatcher = move_dispatcher(config)?;
+    let others = candidates
+        .iter()
+        .filter(|other| other.address != client.address);
+    let moves = others
+        .filter(|other| !other.pinned)
+        .map(|other| (dispatcher, format!("+0,address:{}", other.address)))
+        .collect::<Vec<_>>();
+    send(hypr, config, &moves)
+}
+
+/// Open the --log-launch file for appending, or warn and launch without it
+fn open_launch_log(path: &str) -> Option<File> {
+    let path = Path::new(path);
+    let open = || {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new().create(true).append(true).open(path)
+    };
+    match open() {
+        Ok(file) => Some(file),
+        Err(error) => {
+            eprintln!("warning: could not open {}: {error}", path.display());
+            None
+        }
+    }
+}
+
+/// Launch the command, then wait for its window if asked to
+fn launch_window(
+    hypr: &impl Compositor,
+    config: &RaiseConfig,
+    matches: impl Fn(&Client) -> bool,
+    launch: Launch,
+    known: &[Client],
+) -> raise::Result<Option<String>> {
+    launch.run(hypr)?;
+    let confirm = config.launch_and_wait_for_focus;
+    let focus = config.after_launch_focus || config.wait_for.is_some() || confirm;
+    let require = config.require_launch || confirm;
+    let timeout = match config.wait {
+        Some(wait) => wait,
+        None if focus || require || config.on_launch_only_move => DEFAULT_WAIT,
+        None => return Ok(None),
+    };
+    let start = Instant::now();
+    // Some applications open a throwaway window of another class before the real one. The
+    // class compares like --class does, where aliases are already canonical.
+    let wait_for = config.wait_for.as_ref().map(|class| ClientFilter {
+        class: Some(Pattern::Exact(class.clone())),
+        normalize: config.normalize,
+        class_or_initial: config.class_or_initial,
+        ..ClientFilter::default()
+    });
+    let target = |client: &Client| match &wait_for {
+        Some(filter) => filter.matches(client),
+        None => matches(client),
+    };
+    let window = hypr.wait_for_window(target, known, Duration::from_millis(timeout))?;
+    let moving = window.as_ref().filter(|_| config.on_launch_only_move);
+    if let Some((client, workspace)) = moving.zip(move_target(config)) {
+        let argument = format!("{workspace},address:{}", client.address);
+        hypr.dispatch(move_dispatcher(config)?, &argument)?;
+    }
+    match window {
+        Some(client) if focus => {
+            hypr.focus_window_with(&client.address, config.focus_method)?;
+            if !confirm {
+                return Ok(Some(client.address));
+            }
+            // The window appearing and being asked to focus does not mean it has focus yet
+            let remaining = Duration::from_millis(timeout).saturating_sub(start.elapsed());
+            if hypr.wait_for_focus(&client.address, remaining)? {
+                Ok(Some(client.address))
+            } else {
+                Err(RaiseError::FocusTimedOut {
+                    address: client.address,
+                    timeout,
+                })
+            }
+        }
+        Some(_) => Ok(None),
+        None if require => Err(RaiseError::LaunchTimedOut {
+            command: launch.command_line(),
+            timeout,
+        }),
+        None => {
+            if config.verbose {
+                eprintln!("No matching window appeared within {timeout}ms");
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Warp the cursor to the middle of the window for --cursor-center, going by where it is
+/// once focused since moving it changes its geometry
+fn center_cursor(hypr: &impl Compositor, config: &RaiseConfig, address: &str) -> raise::Result<()> {
+    let active = hypr
+        .active_window()
+        .ok()
+        .filter(|client| client.address == address);
+    match active.as_ref().and_then(Client::center) {
+        Some((x, y)) => hypr.dispatch("movecursor", &format!("{x} {y}")),
+        None => {
+            if config.verbose {
+                eprintln!("Not moving the cursor, the geometry of {address} is unknown");
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Confirm for --verify-focus that the window is the active one, focusing it a second
+/// time when it is not
+fn verify_focus(hypr: &impl Compositor, config: &RaiseConfig, address: &str) -> raise::Result<()> {
+    // Nothing being focused is just another way for focus to go wrong
+    let active = || {
+        hypr.active_window()
+            .map_or_else(|_| "nothing".into(), |client| client.address)
+    };
+    let mut focused = active();
+    if focused != address {
+        if config.verbose {
+            eprintln!("{focused} is focused instead of {address}, trying again");
+        }
+        hypr.focus_window_with(address, config.focus_method)?;
+        focused = active();
+    }
+    if focused == address {
+        Ok(())
+    } else {
+        Err(RaiseError::FocusIntercepted {
+            address: address.into(),
+            active: focused,
+        })
+    }
+}
+
+/// Send --send-keys to the window raise focused, once it has focus
+fn send_keys(
+    hypr: &impl Compositor,
+    config: &RaiseConfig,
+    keys: &Keys,
+    address: &str,
+) -> raise::Result<()> {
+    let timeout = config.wait.unwrap_or(DEFAULT_WAIT);
+    let result = match hypr.wait_for_focus(address, Duration::from_millis(timeout)) {
+        Ok(true) => keys.send(hypr, config.keys_tool, address),
+        Ok(false) => Err(RaiseError::FocusTimedOut {
+            address: address.into(),
+            timeout,
+        }),
+        Err(error) => Err(error),
+    };
+    match result {
+        Err(error) if config.best_effort => {
+            if config.verbose {
+                eprintln!("Could not send {keys}: {error}");
+            }
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Focus a grouped candidate for --join-group, since Hyprland opens new windows into the
+/// focused group
+fn focus_group(
+    hypr: &impl Compositor,
+    config: &RaiseConfig,
+    candidates: &[&Client],
+) -> raise::Result<()> {
+    match candidates.iter().find(|client| client.is_grouped()) {
+        Some(member) => hypr.focus_window(&member.address),
+        None => {
+            if config.verbose {
+                eprintln!("No matching window is in a group, launching outside of one");
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Ask a window to close, then kill its process if --grace-close runs out
+fn close_window(
+    hypr: &impl Compositor,
+    config: &RaiseConfig,
+    client: &Client,
+) -> raise::Result<()> {
+    let address = &client.address;
+    hypr.dispatch("closewindow", &format!("address:{address}"))?;
+    let Some(grace) = config.grace_close else {
+        return Ok(());
+    };
+    if config.verbose {
+        eprintln!("Asked {address} to close, waiting up to {grace}ms");
+    }
+    if hypr.wait_for_close(address, Duration::from_millis(grace))? {
+        if config.verbose {
+            eprintln!("{address} closed");
+        }
+        return Ok(());
+    }
+    if config.verbose {
+        eprintln!("{address} is still open, killing process {}", client.pid);
+    }
+    raise::process::kill(client.pid)
+}
+
+/// Show or hide the special workspace, launching into it first when no window matches
+fn toggle_special(
+    hypr: &impl Compositor,
+    filter: &ClientFilter,
+    launch: &str,
+    name: &str,
+    clients: Option<&[Client]>,
+) -> raise::Result<()> {
+    let running = clients.is_some_and(|clients| !filter.apply(clients).is_empty());
+    if !running {
+        launch_hidden(hypr, launch, name)?;
+    }
+    hypr.dispatch("togglespecialworkspace", name)
+}
+
+/// Launch into a special workspace, silently so that it neither shows nor takes focus
+fn launch_hidden(hypr: &impl Compositor, launch: &str, name: &str) -> raise::Result<()> {
+    hypr.launch(&format!("[workspace special:{name} silent] {launch}"))
+}
+
+/// Run against the backend with the wrappers every backend gets: class aliases, and with
+/// --best-effort carrying on past failed dispatches
+fn stack<C: Compositor>(
+    inner: C,
+    classes: BTreeMap<String, String>,
+    args: &Args,
+    config: &RaiseConfig,
+    timings: &mut Timings,
+) -> Result<()> {
+    let hypr = ClassMap { inner, classes };
+    if config.best_effort {
+        let verbose = config.verbose;
+        run(
+            &BestEffort {
+                inner: hypr,
+                verbose,
+            },
+            args,
+            config,
+            timings,
+        )
+    } else {
+        run(&hypr, args, config, timings)
+    }
+}
+
+/// Keep the clients of the instance with the signature for `--cache`
+fn cached<C: Compositor>(inner: C, config: &RaiseConfig, signature: Option<String>) -> Cached<C> {
+    Cached {
+        inner,
+        dir: state_dir(config),
+        signature,
+        ttl: config.cache,
+    }
+}
+
+fn main() -> Result<()> {
+    // `doctor` has to come first, so it cannot be confused with a command after `--`
+    let argv = std::env::args().collect::<Vec<_>>();
+    if argv.get(1).is_some_and(|arg| arg == "doctor") {
+        let Doctor {} = subcommand("raise doctor", &argv[2..]);
+        return doctor();
+    }
+    #[cfg(unix)]
+    if argv.get(1).is_some_and(|arg| arg == "watch") {
+        return watch(subcommand("raise watch", &argv[2..]));
+    }
+    #[cfg(all(unix, feature = "bench"))]
+    if argv.get(1).is_some_and(|arg| arg == "bench") {
+        return bench(subcommand("raise bench", &argv[2..]));
+    }
+
+    // Get arguments
+    let args: Args = argh::from_env();
+
+    // Report the running Hyprland, which determines the JSON we get back
+    if args.show_version_info {
+        let version = version::detect()?;
+        println!("Hyprland {} (commit {})", version.tag, version.commit);
+        let (major, minor, patch) = version::MINIMUM;
+        println!("raise is tested against v{major}.{minor}.{patch} or newer");
+        return Ok(());
+    }
+
+    let file = ConfigFile::load(args.config.clone())?;
+    let config = RaiseConfig::merge(&file, args.profile.as_deref(), args.layer()?)?;
+    if args.show_config {
+        println!("{}", miniserde::json::to_string(&config));
+        return Ok(());
+    }
+
+    if config.verbose {
+        warn_if_unsupported(version::detect());
+    }
+
+    let mut timings = Timings::default();
+    let classes = class_map(&file, &config)?;
+    let result = if config.all_instances {
+        let instances = AllInstances::discover().into_instances().into_iter();
+        let instances = instances.map(|instance| {
+            let signature = Some(instance.0.clone());
+            cached(instance, &config, signature)
+        });
+        let hypr = AllInstances::new(instances.collect());
+        stack(hypr, classes, &args, &config, &mut timings)
+    } else {
+        let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").ok();
+        let hypr = cached(Hyprctl, &config, signature);
+        stack(hypr, classes, &args, &config, &mut timings)
+    };
+    if config.timings {
+        eprintln!("{timings}");
+    }
+    match result {
+        Err(error) if config.color.enabled() => {
+            eprintln!("{}", Color::Red.paint(format!("Error: {error:?}"), true));
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+/// The canonical class of every aliased class, from the configuration file overlaid with
+/// `--map-class`
+fn class_map(file: &ConfigFile, config: &RaiseConfig) -> raise::Result<BTreeMap<String, String>> {
+    let mut classes = file.class_map.clone().unwrap_or_default();
+    for alias in &config.map_class {
+        match alias.split_once('=') {
+            Some((reported, canonical)) if !reported.is_empty() && !canonical.is_empty() => {
+                classes.insert(reported.to_string(), canonical.to_string());
+            }
+            _ => {
+                return Err(RaiseError::InvalidArgs(format!(
+                    "`{alias}` is not a class alias, expected `reported=canonical`"
+                )));
+            }
+        }
+    }
+    Ok(classes)
+}
+
+/// Parse the arguments of a subcommand, exiting on `--help` or bad arguments as argh does
+fn subcommand<T: FromArgs>(name: &str, rest: &[String]) -> T {
+    let rest = rest.iter().map(String::as_str).collect::<Vec<_>>();
+    T::from_args(&[name], &rest).unwrap_or_else(|exit| {
+        if exit.status.is_ok() {
+            println!("{}", exit.output);
+            std::process::exit(0);
+        }
+        eprintln!("{}", exit.output);
+        std::process::exit(1);
+    })
+}
+
+/// Print min, median and max of listing windows through each backend
+#[cfg(all(unix, feature = "bench"))]
+fn bench(Bench { iterations }: Bench) -> Result<()> {
+    use raise::bench::{self, Backend};
+
+    if iterations == 0 {
+        bail!("--iterations must be at least 1");
+    }
+    println!(
+        "{:<10}{:>12}{:>12}{:>12}",
+        "backend", "min", "median", "max"
+    );
+    for backend in Backend::ALL {
+        let summary = bench::run(backend, iterations)
+            .with_context(|| format!("listing windows through {} failed", backend.name()))?;
+        if let Some(summary) = summary {
+            let [min, median, max] = [summary.min, summary.median, summary.max]
+                .map(|duration| format!("{:.2}ms", duration.as_secs_f64() * 1000.0));
+            println!("{:<10}{min:>12}{median:>12}{max:>12}", backend.name());
+        }
+    }
+    Ok(())
+}
+
+/// Warn about a Hyprland older than raise is tested against, or one whose version is not
+/// known, under --verbose
+fn warn_if_unsupported(version: raise::Result<impl Borrow<version::Version>>) {
+    match version.as_ref().map(Borrow::borrow) {
+        Ok(version) if !version.is_supported() => {
+            let (major, minor, patch) = version::MINIMUM;
+            eprintln!(
+                "warning: Hyprland {} is older than v{major}.{minor}.{patch}, \
+                 output may not parse",
+                version.tag
+            );
+        }
+        Ok(_) => {}
+        Err(error) => eprintln!("warning: could not detect Hyprland version: {error:#}"),
+    }
+}
+
+/// Print a report of every check, failing if any of them did
+fn doctor() -> Result<()> {
+    let checks = raise::doctor::checks();
+    for check in &checks {
+        match &check.result {
+            Ok(found) => println!("ok    {:<10}{found}", check.name),
+            Err(problem) => println!("FAIL  {:<10}{problem}", check.name),
+        }
+    }
+    let failed = checks.iter().filter(|check| check.result.is_err()).count();
+    if failed > 0 {
+        bail!("{failed} of {} checks failed", checks.len());
+    }
+    Ok(())
+}
+
+/// What `raise watch` works out once rather than for every event: the instance it talks
+/// to, its event socket and its version. Each is found when first needed, and a
+/// `configreloaded` event drops them all, to be found anew.
+#[cfg(unix)]
+#[derive(Default)]
+struct Session {
+    instance: Option<hyprctl::Instance>,
+    socket: Option<PathBuf>,
+    version: Option<version::Version>,
+}
+
+#[cfg(unix)]
+impl Session {
+    fn socket(&mut self) -> raise::Result<&Path> {
+        let socket = match self.socket.take() {
+            Some(socket) => socket,
+            None => hyprctl::event_socket_path().ok_or(RaiseError::NoCompositor)?,
+        };
+        Ok(self.socket.insert(socket))
+    }
+
+    /// The instance in the environment, or else the one whose socket was found
+    fn instance(&mut self) -> raise::Result<&hyprctl::Instance> {
+        let instance = match self.instance.take() {
+            Some(instance) => instance,
+            None => match std::env::var("HYPRLAND_INSTANCE_SIGNATURE") {
+                Ok(signature) => hyprctl::Instance(signature),
+                Err(_) => {
+                    let dir = self.socket()?.parent().and_then(Path::file_name);
+                    let signature = dir.ok_or(RaiseError::NoCompositor)?;
+                    hyprctl::Instance(signature.to_string_lossy().into_owned())
+                }
+            },
+        };
+        Ok(self.instance.insert(instance))
+    }
+
+    fn version(&mut self) -> raise::Result<&version::Version> {
+        let version = match self.version.take() {
+            Some(version) => version,
+            None => self.instance()?.version()?,
+        };
+        Ok(self.version.insert(version))
+    }
+
+    fn invalidate(&mut self) {
+        *self = Session::default();
+    }
+}
+
+/// Listen for events and act on the windows of the class, where a failed query or
+/// dispatch, like one to a window closed already, only skips that event
+#[cfg(unix)]
+fn watch(watch: Watch) -> Result<()> {
+    let actions = [watch.on_open, watch.on_urgent, watch.on_active];
+    if actions.iter().all(Option::is_none) && !watch.track_urgent {
+        bail!("raise watch requires --on-open, --on-urgent, --on-active or --track-urgent");
+    }
+    if actions.iter().any(Option::is_some) && watch.class.is_none() {
+        bail!("raise watch requires --class for --on-open, --on-urgent and --on-active");
+    }
+    let dir = watch
+        .state_dir
+        .as_deref()
+        .map_or_else(cache::runtime_dir, PathBuf::from);
+    // Windows asked for attention before the watch started, if at all, are not known
+    let mut urgent = BTreeSet::new();
+    if watch.track_urgent {
+        urgent::store(&dir, &urgent);
+    }
+    let mut session = Session::default();
+    let socket = session.socket()?.to_path_buf();
+    if watch.verbose {
+        warn_if_unsupported(session.version());
+    }
+    raise::events::listen(&socket, |event| {
+        if event == Event::ConfigReloaded {
+            session.invalidate();
+            if watch.verbose {
+                warn_if_unsupported(session.version());
+            }
+        }
+        if watch.track_urgent && track_urgent(&mut urgent, &event) {
+            urgent::store(&dir, &urgent);
+        }
+        let hypr = match session.instance() {
+            Ok(hypr) => hypr,
+            Err(error) => {
+                if watch.verbose {
+                    eprintln!("Skipping {event:?}: {error}");
+                }
+                return Ok(());
+            }
+        };
+        act_on(hypr, &watch, &event);
+        unpeek(hypr, &watch, &dir);
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Put back the windows whose --peek-timeout is up, which an event is as good a time as
+/// any to notice, skipping failures like act_on does
+#[cfg(unix)]
+fn unpeek(hypr: &impl Compositor, watch: &Watch, dir: &Path) {
+    let result = unpeek_expired(hypr, dir).and_then(|dispatches| match dispatches.as_slice() {
+        [] => Ok(()),
+        [(dispatcher, argument)] => hypr.dispatch(dispatcher, argument),
+        _ => hypr.dispatch_batch(&dispatches),
+    });
+    if let (Err(error), true) = (result, watch.verbose) {
+        eprintln!("Could not put back peeked windows: {error}");
+    }
+}
+
+/// Act on one event, where failing to look the window up or to dispatch only skips it,
+/// so the watch keeps going
+#[cfg(unix)]
+fn act_on(hypr: &impl Compositor, watch: &Watch, event: &Event) {
+    let (dispatcher, argument) = match watched(hypr, watch, event) {
+        Ok(Some(dispatch)) => dispatch,
+        Ok(None) => return,
+        Err(error) => {
+            if watch.verbose {
+                eprintln!("Skipping {event:?}: {error}");
+            }
+            return;
+        }
+    };
+    if watch.verbose {
+        eprintln!("{event:?}: {dispatcher} {argument}");
+    }
+    match hypr.dispatch(dispatcher, &argument) {
+        Err(error) if watch.verbose => eprintln!("{error}"),
+        _ => {}
+    }
+}
+
+/// Note down windows asking for attention until they get it or close, returning whether
+/// anything changed
+#[cfg(unix)]
+fn track_urgent(urgent: &mut BTreeSet<String>, event: &Event) -> bool {
+    match event {
+        Event::Urgent { address } => urgent.insert(address.clone()),
+        Event::ActiveWindowV2 {
+            address: Some(address),
+        }
+        | Event::CloseWindow { address } => urgent.remove(address),
+        _ => false,
+    }
+}
+
+/// The dispatch an event calls for, if it is about a window of the class
+#[cfg(unix)]
+fn watched(
+    hypr: &impl Compositor,
+    watch: &Watch,
+    event: &Event,
+) -> raise::Result<Option<(&'static str, String)>> {
+    let Some(watched) = &watch.class else {
+        return Ok(None);
+    };
+    let (action, address) = match event {
+        Event::OpenWindow { address, class } if class == watched => (watch.on_open, address),
+        Event::Urgent { address } if watch.on_urgent.is_some() => {
+            let clients = hypr.clients()?;
+            let client = clients.iter().find(|client| client.address == *address);
+            if client.is_none_or(|client| client.class != *watched) {
+                return Ok(None);
+            }
+            (watch.on_urgent, address)
+        }
+        Event::ActiveWindow { class } if class == watched => {
+            return Ok(watch.on_active.and_then(|action| action.dispatch(None)));
+        }
+        _ => return Ok(None),
+    };
+    Ok(action.and_then(|action| action.dispatch(Some(address))))
+}
+
+/// Wall-clock time spent in each phase of an invocation, for --timings
+#[derive(Default)]
+struct Timings {
+    clients: Duration,
+    active: Duration,
+    decide: Duration,
+    dispatch: Duration,
+}
+
+impl Timings {
+    fn measure<T>(phase: &mut Duration, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let value = f();
+        *phase += start.elapsed();
+        value
+    }
+}
+
+impl std::fmt::Display for Timings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "timings clients={:.2}ms active={:.2}ms decide={:.2}ms dispatch={:.2}ms",
+            ms(self.clients),
+            ms(self.active),
+            ms(self.decide),
+            ms(self.dispatch),
+        )
+    }
+}
+
+/// What to do once the state of the windows is known
+enum Decision<'a> {
+    Focus(&'a Client),
+    FocusLast,
+    Minimize(&'a Client),
+    Launch(Launch<'a>),
+    Nothing,
+}
+
+impl Decision<'_> {
+    /// One line for --verbose, green for focusing and yellow for launching
+    fn summary(&self, color: bool) -> String {
+        match self {
+            Decision::Focus(client) => {
+                let text = format!("Focusing {} ({})", client.address, client.class);
+                Color::Green.paint(text, color)
+            }
+            Decision::FocusLast => Color::Green.paint("Focusing the previous window", color),
+            Decision::Minimize(client) => {
+                let text = format!("Minimizing {} ({})", client.address, client.class);
+                Color::Green.paint(text, color)
+            }
+            Decision::Launch(launch) => Color::Yellow.paint(launch_summary(launch), color),
+            Decision::Nothing => "Nothing to do".into(),
+        }
+    }
+}
+
+/// What --explain needs to know about how a decision came about
+struct Explanation<'a> {
+    config: &'a RaiseConfig,
+    /// What the candidates matched, for the sentence
+    criteria: String,
+    /// Whether the menu picks between the candidates
+    menu: bool,
+    candidates: &'a [&'a Client],
+}
+
+impl Explanation<'_> {
+    /// The candidate number of a window, starting at 1
+    fn position(&self, address: &str) -> Option<usize> {
+        let position = self
+            .candidates
+            .iter()
+            .position(|client| client.address == address);
+        position.map(|index| index + 1)
+    }
+
+    /// A sentence on the state of the windows, then what is done about it
+    fn sentence(&self, decision: &Decision, active: Option<&Client>) -> String {
+        let (config, criteria, count) = (self.config, &self.criteria, self.candidates.len());
+        let active = active.and_then(|active| Some((active, self.position(&active.address)?)));
+        let state = match active {
+            Some((active, at)) => format!(
+                "Active window {} matches {criteria} and is candidate #{at} of {count}",
+                active.address
+            ),
+            None if count == 0 => format!("No window matches {criteria}"),
+            None if count == 1 => format!("The active window does not match {criteria}, 1 does"),
+            None => format!("The active window does not match {criteria}, {count} do"),
+        };
+        let action = match decision {
+            Decision::Focus(client) => {
+                let how = if config.index.is_some() {
+                    "focusing --index candidate"
+                } else if config.newest {
+                    "focusing the newest candidate"
+                } else if active.is_some_and(|(active, _)| active.address != client.address)
+                    && config.urgent_first
+                    && client.is_urgent()
+                {
+                    "focusing urgent candidate"
+                } else if active.is_some() {
+                    "focusing next candidate"
+                } else {
+                    "focusing first candidate"
+                };
+                let at = self.position(&client.address).unwrap_or_default();
+                format!(
+                    "{how} #{at}, {} on workspace {}",
+                    client.address, client.workspace.name
+                )
+            }
+            Decision::FocusLast => "going back to the previously focused window".into(),
+            Decision::Minimize(_) => format!("minimizing it to {}", minimize::WORKSPACE),
+            Decision::Launch(launch) if config.force_launch => {
+                format!(
+                    "launching `{}` anyway, as --force-launch asks",
+                    launch.command_line()
+                )
+            }
+            Decision::Launch(launch) => match config.index {
+                Some(index) if count > 0 => {
+                    format!(
+                        "there is no candidate #{index}, launching `{}`",
+                        launch.command_line()
+                    )
+                }
+                _ => format!("launching `{}`", launch.command_line()),
+            },
+            Decision::Nothing if self.menu => format!("would prompt among {count} candidates"),
+            Decision::Nothing => "there is nothing to launch".into(),
+        };
+        format!("{state}; {action}.")
+    }
+}
+
+/// Where the active window stands, which is all cycling goes by
+enum Focused {
+    /// The candidate at this position
+    Candidate(usize),
+    /// A window that is not a candidate, of another class or filtered out
+    Other,
+    /// No window at all, like on an empty workspace
+    Nothing,
+}
+
+impl Focused {
+    fn of(candidates: &[&Client], active: Option<&Client>) -> Self {
+        // A window mid-teardown may be reported without an address, focusing nothing
+        let Some(active) = active.filter(|active| active.has_address()) else {
+            return Focused::Nothing;
+        };
+        match candidates
+            .iter()
+            .position(|client| client.address == active.address)
+        {
+            Some(index) => Focused::Candidate(index),
+            None => Focused::Other,
+        }
+    }
+}
+
+/// Decide without --index, --newest or a menu, going by the active window
+fn cycle<'a>(
+    config: &RaiseConfig,
+    candidates: &[&'a Client],
+    active: Option<&Client>,
+    fallback: Decision<'a>,
+) -> Decision<'a> {
+    let focused = Focused::of(candidates, active);
+    // A window asking for attention beats the usual order
+    let urgent = candidates.iter().find(|client| {
+        let is_active = active.is_some_and(|active| active.address == client.address);
+        config.urgent_first && client.is_urgent() && !is_active
+    });
+    if let Some(urgent) = urgent {
+        return Decision::Focus(urgent);
+    }
+    match focused {
+        // Flip back to whatever was focused before
+        Focused::Candidate(_) if config.toggle_last => Decision::FocusLast,
+        // Like clicking an application on a taskbar a second time
+        Focused::Candidate(index) if config.raise_or_minimize => {
+            Decision::Minimize(candidates[index])
+        }
+        // Focus the next candidate, which is the same one again when there is only one
+        Focused::Candidate(index) => Decision::Focus(candidates[(index + 1) % candidates.len()]),
+        // Bring the first candidate over whatever else is focused, otherwise launch
+        Focused::Other => candidates
+            .first()
+            .map_or(fallback, |client| Decision::Focus(client)),
+        // Nothing to leave, so the same goes
+        Focused::Nothing => candidates
+            .first()
+            .map_or(fallback, |client| Decision::Focus(client)),
+    }
+}
+
+fn launch_summary(launch: &Launch) -> String {
+    match launch {
+        Launch::Exec(_) => format!("Launching {}", launch.command_line()),
+        Launch::Spawn(_) => format!("Spawning {}", launch.command_line()),
+    }
+}
+
+/// Act, or with --plan print what acting would do
+fn run(
+    hypr: &impl Compositor,
+    args: &Args,
+    config: &RaiseConfig,
+    timings: &mut Timings,
+) -> Result<()> {
+    if !args.plan {
+        return execute(hypr, args, config, timings);
+    }
+    let exclusive = [
+        ("--explain", args.explain),
+        ("--count", args.count),
+        ("--list", args.list),
+    ];
+    for (name, given) in exclusive {
+        if given {
+            let message = format!("--plan and {name} are mutually exclusive");
+            return Err(RaiseError::InvalidArgs(message).into());
+        }
+    }
+    // What a launch opens is unknown, so there is nothing to require of it
+    let config = RaiseConfig {
+        require_launch: false,
+        launch_and_wait_for_focus: false,
+        ..config.clone()
+    };
+    let planner = Planner::new(hypr);
+    execute(&planner, args, &config, timings)?;
+    let ops = planner.ops().iter().map(Op::to_json).collect();
+    println!("{}", miniserde::json::to_string(&Value::Array(ops)));
+    Ok(())
+}
+
+/// What every mode of an invocation goes by: the compositor it acts on, and the options
+/// it was given
+struct Invocation<'a, C> {
+    hypr: &'a C,
+    args: &'a Args,
+    config: &'a RaiseConfig,
+}
+
+impl<C> Clone for Invocation<'_, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for Invocation<'_, C> {}
+
+fn execute(
+    hypr: &impl Compositor,
+    args: &Args,
+    config: &RaiseConfig,
+    timings: &mut Timings,
+) -> Result<()> {
+    validate(config, args)?;
+    let run = Invocation { hypr, args, config };
+    let (command, spawned) = launch_commands(config, args)?;
+    let log = spawned
+        .as_ref()
+        .and(config.log_launch.as_deref())
+        .and_then(open_launch_log);
+    let launch = match &spawned {
+        Some(argv) => Some(Launch::Spawn(Spawn {
+            argv,
+            detach: config.detach,
+            log: log.as_ref(),
+            cwd: config.cwd.as_deref().map(Path::new),
+        })),
+        None => command.as_deref().map(Launch::Exec),
+    };
+
+    // Every query would fail outside Hyprland, which would launch duplicates
+    if let Err(error) = hypr.check_instance() {
+        return launch_without_compositor(run, launch, error, timings);
+    }
+    check_move(hypr, config)?;
+    // A known address skips matching entirely
+    if let Some(address) = &args.address {
+        return focus_address(run, address, timings);
+    }
+
+    let keys = match &config.send_keys {
+        Some(keys) => Some(keys.parse::<Keys>().map_err(RaiseError::InvalidArgs)?),
+        None => None,
+    };
+    let current;
+    let class = if config.current_class {
+        let active = Timings::measure(&mut timings.active, || hypr.active_window());
+        let Some(active) = active.ok().filter(Client::has_address) else {
+            // Without a focused window there is no class to cycle through
+            if config.verbose {
+                eprintln!("No window is focused, nothing to do for --current-class");
+            }
+            return Ok(());
+        };
+        current = active;
+        Some(current.class.as_str())
+    } else {
+        config.class.as_deref()
+    };
+    let filter = client_filter(run, class, timings)?;
+
+    if args.count || args.list {
+        return count_or_list(run, &filter, timings);
+    }
+    if bounced(run, class) {
+        return Ok(());
+    }
+    let (clients, prefetched) = query_windows(run, timings)?;
+    if clients.is_some() {
+        put_back_expired(run, timings)?;
+    }
+    // Scratchpad mode always ends up toggling the special workspace
+    if let (Some(name), Some(class), Some(launch)) =
+        (&config.toggle_special, class, command.as_deref())
+    {
+        return scratchpad(
+            run,
+            &filter,
+            class,
+            launch,
+            name,
+            clients.as_deref(),
+            timings,
+        );
+    }
+    if let (Some(name), Some(launch)) = (&config.launch_hidden, command.as_deref()) {
+        return warm_up(run, &filter, launch, name, clients.as_deref(), timings);
+    }
+    let Some(clients) = clients else {
+        return launch_blindly(run, launch, timings);
+    };
+
+    let focused = raise_or_launch(run, class, &filter, launch, &clients, prefetched, timings)?;
+    if let Some(address) = &focused {
+        follow_up(run, keys.as_ref(), address, timings)?;
+    }
+    Ok(())
+}
+
+/// Close, put back, focus or launch, whichever the matching windows call for, returning
+/// the window raise focused itself, if any
+fn raise_or_launch(
+    run: Invocation<impl Compositor>,
+    class: Option<&str>,
+    filter: &ClientFilter,
+    launch: Option<Launch>,
+    clients: &[Client],
+    prefetched: Prefetched,
+    timings: &mut Timings,
+) -> Result<Option<String>> {
+    let Invocation { args, config, .. } = run;
+    let matching = Timings::measure(&mut timings.decide, || filter.apply(clients));
+    if config.verbose {
+        for client in clients.iter().filter(|client| !client.has_address()) {
+            eprintln!(
+                "Skipping {} window with address `{}`",
+                client.class, client.address
+            );
+        }
+    }
+    let mut candidates = matching.clone();
+    config.order.sort(&mut candidates, config.seed);
+    if config.close || config.grace_close.is_some() {
+        close_matching(run, &candidates, &matching, prefetched, timings)?;
+        return Ok(None);
+    }
+    if config.peek && put_back_peeked(run, clients, &matching, timings)? {
+        return Ok(None);
+    }
+
+    // The count picks which of --on-none, --on-one and --on-many applies
+    let config = &with_policy(config, candidates.len());
+    let run = Invocation { config, ..run };
+    let default_menu = config.select_menu.then(raise::menu::default_command);
+    let menu = config.menu.as_deref().or(default_menu);
+    let fallback = match launch {
+        Some(launch) if !config.no_launch => Decision::Launch(launch),
+        _ => Decision::Nothing,
+    };
+    let decision = decide(
+        run,
+        &candidates,
+        &matching,
+        menu,
+        fallback,
+        prefetched,
+        timings,
+    )?;
+    if args.explain {
+        explain(run, class, menu.is_some(), &candidates, &decision);
+        return Ok(None);
+    }
+    if config.verbose {
+        eprintln!("{}", decision.summary(config.color.enabled()));
+    }
+    let focused = Timings::measure(&mut timings.dispatch, || {
+        act(run, decision, &candidates, clients, filter)
+    })?;
+    Ok(focused)
+}
+
+/// The command Hyprland runs for --launch and the program raise spawns itself instead,
+/// either of them with the placeholders filled in
+fn launch_commands(
+    config: &RaiseConfig,
+    args: &Args,
+) -> raise::Result<(Option<String>, Option<Vec<String>>)> {
+    let class = config.class.as_deref();
+    let title = config.title.as_deref();
+    // A shell command line gets the values quoted, each staying one word whatever it holds
+    let (class_word, title_word) = (class.map(shell_quote), title.map(shell_quote));
+    let quoted = [
+        ("class", class_word.as_deref()),
+        ("title", title_word.as_deref()),
+    ];
+    let (command, listed) = match &config.launch {
+        Some(LaunchCommand::Shell(template)) => {
+            (Some(launch::substitute(template, &quoted)?), None)
+        }
+        Some(LaunchCommand::Argv(argv)) => {
+            let values = [("class", class), ("title", title)];
+            let argv = argv
+                .iter()
+                .map(|arg| launch::substitute(arg, &values))
+                .collect::<raise::Result<Vec<_>>>()?;
+            (None, Some(argv))
+        }
+        None => (None, None),
+    };
+    // Programs raise spawns itself, as opposed to commands Hyprland runs
+    let spawned = match (command.as_deref(), listed, args.command.as_slice()) {
+        (Some(command), _, _) => config.launcher.argv(command)?,
+        (None, Some(argv), _) => Some(argv),
+        (None, None, []) => None,
+        (None, None, argv) => Some(argv.to_vec()),
+    };
+    // Hyprland starts commands in its own working directory
+    let command = match config.cwd.as_deref().map(Path::new) {
+        Some(cwd) => command.map(|command| launch::in_dir(&command, cwd)),
+        None => command,
+    };
+    Ok((command, spawned))
+}
+
+/// Outside Hyprland there is nothing to match, so only --launch-on-no-compositor does
+/// anything, which is launching
+fn launch_without_compositor(
+    run: Invocation<impl Compositor>,
+    launch: Option<Launch>,
+    error: RaiseError,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Invocation { hypr, args, config } = run;
+    match launch {
+        Some(launch) if config.launch_on_no_compositor && args.explain => {
+            println!(
+                "No Hyprland instance found; launching `{}`.",
+                launch.command_line()
+            );
+            Ok(())
+        }
+        Some(launch) if config.launch_on_no_compositor => {
+            if config.verbose {
+                eprintln!(
+                    "{}",
+                    Decision::Launch(launch).summary(config.color.enabled())
+                );
+            }
+            Timings::measure(&mut timings.dispatch, || launch.run(hypr))?;
+            Ok(())
+        }
+        _ => Err(error.into()),
+    }
+}
+
+/// Focus the window at --address, looking it up first unless --no-verify
+fn focus_address(
+    run: Invocation<impl Compositor>,
+    address: &str,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Invocation { hypr, args, config } = run;
+    let color = config.color.enabled();
+    if args.no_verify {
+        if args.explain {
+            println!("Window {address} is not looked up; focusing it.");
+            return Ok(());
+        }
+        if config.verbose {
+            eprintln!(
+                "{}",
+                Color::Green.paint(format!("Focusing {address}"), color)
+            );
+        }
+        Timings::measure(&mut timings.dispatch, || {
+            raise_window(hypr, config, address, None)
+        })?;
+        return Ok(());
+    }
+    let clients = Timings::measure(&mut timings.clients, || hypr.clients())
+        .context("Could not verify --address, pass --no-verify to skip")?;
+    let Some(client) = clients.iter().find(|client| client.address == *address) else {
+        bail!("No window with address {address}");
+    };
+    if args.explain {
+        println!("Window {address} ({}) is open; focusing it.", client.class);
+        return Ok(());
+    }
+    if config.verbose {
+        eprintln!("{}", Decision::Focus(client).summary(color));
+    }
+    Timings::measure(&mut timings.dispatch, || {
+        raise_window(hypr, config, address, Some(client))
+    })?;
+    Ok(())
+}
+
+/// The windows the options select, with the class resolved already
+fn client_filter(
+    run: Invocation<impl Compositor>,
+    class: Option<&str>,
+    timings: &mut Timings,
+) -> raise::Result<ClientFilter> {
+    let Invocation { hypr, config, .. } = run;
+    let conditions = config
+        .match_json
+        .iter()
+        .map(|condition| JsonMatch::new(condition))
+        .collect::<raise::Result<Vec<_>>>()?;
+    // Every field needs the untyped JSON, which only gets queried when asked for
+    let only = if conditions.is_empty() {
+        None
+    } else {
+        let values = Timings::measure(&mut timings.clients, || hypr.client_values())?;
+        Some(JsonMatch::addresses(&conditions, &values))
+    };
+    let classless = config.classless.then(|| Pattern::Exact(String::new()));
+    Ok(ClientFilter {
+        class: class
+            .map(|class| Pattern::Exact(class.into()))
+            .or(classless),
+        normalize: config.normalize,
+        class_or_initial: config.class_or_initial,
+        title: config
+            .title
+            .as_deref()
+            .map(|title| Pattern::Substring(title.into())),
+        workspace: config
+            .workspace_pattern
+            .as_deref()
+            .map(workspace_pattern)
+            .transpose()?,
+        xwayland: config.xwayland,
+        floating: config.floating,
+        special: config.only_special.then_some(true),
+        exclude_swallowed: config.skip_swallowed,
+        only,
+        ..ClientFilter::default()
+    })
+}
+
+/// Print how many windows match for --count, or the windows themselves for --list. Both
+/// are read-only, so hyprctl failures are real errors here.
+fn count_or_list(
+    run: Invocation<impl Compositor>,
+    filter: &ClientFilter,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Invocation { hypr, args, config } = run;
+    let clients = Timings::measure(&mut timings.clients, || hypr.clients())?;
+    let mut matching = filter.apply(&clients);
+    if args.count {
+        println!("{}", matching.len());
+        return Ok(());
+    }
+    config.order.sort(&mut matching, config.seed);
+    let format = args.format.unwrap_or_else(ListFormat::detect);
+    print!("{}", format.render(&matching));
+    Ok(())
+}
+
+/// Whether to do nothing for --debounce. A second press right after the first is more
+/// likely a slip than a wish to cycle.
+fn bounced(run: Invocation<impl Compositor>, class: Option<&str>) -> bool {
+    let Invocation { args, config, .. } = run;
+    if config.debounce == 0 || args.explain || args.plan {
+        return false;
+    }
+    let key = debounce_key(config, class);
+    if !debounce::bounced(&state_dir(config), &key, config.debounce) {
+        return false;
+    }
+    if config.verbose {
+        eprintln!("Acted less than {}ms ago, doing nothing", config.debounce);
+    }
+    true
+}
+
+/// The active window when it came with the windows, in the same round trip
+type Prefetched = Option<raise::Result<Client>>;
+
+/// The windows, and the active window too when it comes in the same round trip. Without
+/// the windows, because hyprctl failed, there are no clients to work with, unless that
+/// is an error for --strict.
+fn query_windows(
+    run: Invocation<impl Compositor>,
+    timings: &mut Timings,
+) -> Result<(Option<Vec<Client>>, Prefetched)> {
+    let Invocation { hypr, config, .. } = run;
+    // Cycling and closing also need the active window
+    let needs_active = config.index.is_none() && !config.force_launch && !config.newest;
+    let mut prefetched = None;
+    let state = || {
+        if !needs_active {
+            return hypr.clients();
+        }
+        hypr.state().map(|(clients, active)| {
+            prefetched = Some(active);
+            clients
+        })
+    };
+    let mut clients = match Timings::measure(&mut timings.clients, state) {
+        Ok(clients) => Some(clients),
+        Err(error @ RaiseError::ParseFailed(_)) => return Err(error.into()),
+        Err(error) if config.strict => {
+            let context = "Could not list windows, --strict refuses to act without them";
+            return Err(error).context(context);
+        }
+        Err(_) => None,
+    };
+    if let Some(clients) = clients.as_mut().filter(|_| config.urgent_first) {
+        urgent::mark(&state_dir(config), clients);
+    }
+    Ok((clients, prefetched))
+}
+
+/// Put back earlier peeks whose timeout is up, before anything else happens
+fn put_back_expired(run: Invocation<impl Compositor>, timings: &mut Timings) -> Result<()> {
+    let Invocation { hypr, args, config } = run;
+    if args.plan || args.explain {
+        return Ok(());
+    }
+    let dispatches = unpeek_expired(hypr, &state_dir(config))?;
+    if config.verbose && !dispatches.is_empty() {
+        eprintln!("Putting back windows whose --peek-timeout is up");
+    }
+    Timings::measure(&mut timings.dispatch, || send(hypr, config, &dispatches))?;
+    Ok(())
+}
+
+/// Toggle the special workspace for --toggle-special, launching into it first when no
+/// window of the class runs
+fn scratchpad(
+    run: Invocation<impl Compositor>,
+    filter: &ClientFilter,
+    class: &str,
+    launch: &str,
+    name: &str,
+    clients: Option<&[Client]>,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Invocation { hypr, args, .. } = run;
+    if args.explain {
+        let running = clients.is_some_and(|clients| !filter.apply(clients).is_empty());
+        if running {
+            println!("A {class} window exists; toggling special:{name}.");
+        } else {
+            println!(
+                "No {class} window exists; launching `{launch}` into it and toggling \
+                      special:{name}."
+            );
+        }
+        return Ok(());
+    }
+    Timings::measure(&mut timings.dispatch, || {
+        toggle_special(hypr, filter, launch, name, clients)
+    })?;
+    Ok(())
+}
+
+/// Launch into the special workspace for --launch-hidden unless a matching window exists.
+/// Warming up only ever launches, revealing is up to --toggle-special.
+fn warm_up(
+    run: Invocation<impl Compositor>,
+    filter: &ClientFilter,
+    launch: &str,
+    name: &str,
+    clients: Option<&[Client]>,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Invocation { hypr, args, config } = run;
+    let running = clients.is_some_and(|clients| !filter.apply(clients).is_empty());
+    if args.explain && running {
+        println!("A matching window exists; doing nothing.");
+    } else if args.explain {
+        println!("No window matches; launching `{launch}` into special:{name}.");
+    } else if running {
+        if config.verbose {
+            eprintln!("A matching window exists, not launching into special:{name}");
+        }
+    } else {
+        Timings::measure(&mut timings.dispatch, || launch_hidden(hypr, launch, name))?;
+    }
+    Ok(())
+}
+
+/// Launch without looking at the windows, which is all there is to do when hyprctl
+/// returned none
+fn launch_blindly(
+    run: Invocation<impl Compositor>,
+    launch: Option<Launch>,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Invocation { hypr, args, config } = run;
+    let launch = launch.filter(|_| !config.no_launch);
+    if args.explain {
+        let action = match launch {
+            Some(launch) => format!("launching `{}`", launch.command_line()),
+            None => "doing nothing".into(),
+        };
+        println!("hyprctl returned no windows; {action}.");
+        return Ok(());
+    }
+    if let Some(launch) = launch {
+        if config.verbose {
+            eprintln!(
+                "{}",
+                Decision::Launch(launch).summary(config.color.enabled())
+            );
+        }
+        Timings::measure(&mut timings.dispatch, || launch.run(hypr))?;
+    }
+    Ok(())
+}
+
+/// The active window, from the round trip that listed the windows if it came with them
+fn active_window(
+    hypr: &impl Compositor,
+    prefetched: Prefetched,
+    timings: &mut Timings,
+) -> raise::Result<Client> {
+    prefetched.unwrap_or_else(|| Timings::measure(&mut timings.active, || hypr.active_window()))
+}
+
+/// Close the window --close picks: the one at --index, the newest with --newest, or else
+/// the focused one when it matches and the first candidate when it does not
+fn close_matching(
+    run: Invocation<impl Compositor>,
+    candidates: &[&Client],
+    matching: &[&Client],
+    prefetched: Prefetched,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Invocation { hypr, args, config } = run;
+    let target = match config.index {
+        Some(index) => candidates.get(index - 1).copied(),
+        None if config.newest => Client::newest(matching),
+        None => {
+            let active = active_window(hypr, prefetched, timings);
+            let address = active.as_ref().map_or("", |client| client.address.as_str());
+            let active = candidates.iter().find(|client| client.address == *address);
+            active.or(candidates.first()).copied()
+        }
+    };
+    match target {
+        Some(client) if args.explain => {
+            println!(
+                "Closing {} on workspace {}.",
+                client.address, client.workspace.name
+            );
+        }
+        Some(client) => {
+            Timings::measure(&mut timings.dispatch, || close_window(hypr, config, client))?
+        }
+        None if args.explain => println!("No window matches; nothing to close."),
+        None if config.verbose => eprintln!("No matching window to close"),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Put back what an earlier --peek floated, if it was one of the matching windows,
+/// returning whether it was
+fn put_back_peeked(
+    run: Invocation<impl Compositor>,
+    clients: &[Client],
+    matching: &[&Client],
+    timings: &mut Timings,
+) -> Result<bool> {
+    let Invocation { hypr, args, config } = run;
+    // Windows closed since have nothing left to put back
+    let open = peek::load(&state_dir(config))
+        .into_iter()
+        .filter(|(address, _)| clients.iter().any(|client| client.address == *address));
+    let (back, kept): (BTreeMap<_, _>, BTreeMap<_, _>) =
+        open.partition(|(address, _)| matching.iter().any(|client| client.address == *address));
+    if !args.plan && !args.explain {
+        peek::store(&state_dir(config), &kept);
+    }
+    if back.is_empty() {
+        return Ok(false);
+    }
+    let addresses = back.keys().cloned().collect::<Vec<_>>().join(", ");
+    if args.explain {
+        println!("Already peeking at {addresses}; putting it back.");
+        return Ok(true);
+    }
+    if config.verbose {
+        eprintln!("Putting back {addresses}");
+    }
+    let dispatches = back
+        .iter()
+        .flat_map(|(address, peeked)| peeked.restore(address))
+        .collect::<Vec<_>>();
+    Timings::measure(&mut timings.dispatch, || send(hypr, config, &dispatches))?;
+    Ok(true)
+}
+
+/// What to do about the candidates, where `fallback` is what to do when none will do
+fn decide<'a>(
+    run: Invocation<impl Compositor>,
+    candidates: &[&'a Client],
+    matching: &[&'a Client],
+    menu: Option<&str>,
+    fallback: Decision<'a>,
+    prefetched: Prefetched,
+    timings: &mut Timings,
+) -> Result<Decision<'a>> {
+    let Invocation { hypr, args, config } = run;
+    Ok(match config.index {
+        // A new instance is wanted regardless of what is running
+        _ if config.force_launch => fallback,
+        // Absolute selection does not depend on the active window
+        Some(index) => match candidates.get(index - 1) {
+            Some(client) => Decision::Focus(client),
+            None if config.no_launch => {
+                bail!(
+                    "No window at --index {index}, only {} match",
+                    candidates.len()
+                )
+            }
+            None => fallback,
+        },
+        None if config.newest => Client::newest(matching).map_or(fallback, Decision::Focus),
+        // Picking only makes sense between several windows, and --explain only says so
+        None if menu.is_some() && candidates.len() > 1 && args.explain => Decision::Nothing,
+        None if menu.is_some() && candidates.len() > 1 => {
+            match hypr.choose(menu.unwrap_or_default(), candidates)? {
+                Some(client) => Decision::Focus(client),
+                None => Decision::Nothing,
+            }
+        }
+        None => {
+            // Are we currently focusing a matching window?
+            let active = active_window(hypr, prefetched, timings);
+            Timings::measure(&mut timings.decide, || {
+                cycle(config, candidates, active.as_ref().ok(), fallback)
+            })
+        }
+    })
+}
+
+/// Say what the decision is and how it came about for --explain
+fn explain(
+    run: Invocation<impl Compositor>,
+    class: Option<&str>,
+    menu: bool,
+    candidates: &[&Client],
+    decision: &Decision,
+) {
+    let Invocation { hypr, config, .. } = run;
+    let active = hypr.active_window().ok();
+    let criteria = match class {
+        Some(class) => format!("class '{class}'"),
+        None => "the filters".into(),
+    };
+    let explanation = Explanation {
+        config,
+        criteria,
+        menu: menu && candidates.len() > 1,
+        candidates,
+    };
+    println!("{}", explanation.sentence(decision, active.as_ref()));
+    if config.verbose {
+        for (index, client) in candidates.iter().enumerate() {
+            let (address, class, workspace) =
+                (&client.address, &client.class, &client.workspace.name);
+            println!(
+                "  #{} {address} ({class}) on workspace {workspace}",
+                index + 1
+            );
+        }
+    }
+}
+
+/// Carry out the decision, returning the window raise focused itself, if any
+fn act(
+    run: Invocation<impl Compositor>,
+    decision: Decision,
+    candidates: &[&Client],
+    clients: &[Client],
+    filter: &ClientFilter,
+) -> raise::Result<Option<String>> {
+    let Invocation { hypr, args, config } = run;
+    match decision {
+        Decision::Focus(client) => {
+            if config.raise_all {
+                // The window to focus goes last so it ends up on top of the group
+                let others = candidates
+                    .iter()
+                    .filter(|other| other.address != client.address);
+                let addresses = others
+                    .chain([&client])
+                    .map(|client| client.address.as_str());
+                hypr.raise_to_top(addresses)?;
+            }
+            if config.gather {
+                // Moving windows around can move focus, so look before
+                let previous = if config.keep_focus {
+                    hypr.active_window().ok()
+                } else {
+                    None
+                };
+                gather(hypr, config, client, candidates)?;
+                if config.keep_focus {
+                    let moves = prepare_dispatches(config, &client.address, Some(client))?;
+                    send(hypr, config, &moves)?;
+                    if let Some(previous) = previous {
+                        hypr.focus_window_with(&previous.address, config.focus_method)?;
+                    }
+                    return Ok(None);
+                }
+            }
+            if config.raise_or_minimize {
+                if let Some((dispatcher, argument)) = unminimize(config, client, !args.plan) {
+                    hypr.dispatch(dispatcher, &argument)?;
+                }
+            }
+            raise_window(hypr, config, &client.address, Some(client))?;
+            if config.peek {
+                // The layout is remembered first, so a failed float can still be undone
+                if !args.plan {
+                    let mut peeked = peek::load(&state_dir(config));
+                    let ours = Peeked::of(client, config.peek_timeout);
+                    peeked.insert(client.address.clone(), ours);
+                    peek::store(&state_dir(config), &peeked);
+                }
+                send(hypr, config, &peek::dispatches(client))?;
+            }
+            if config.verify_focus {
+                verify_focus(hypr, config, &client.address)?;
+            }
+            if config.cursor_center {
+                center_cursor(hypr, config, &client.address)?;
+            }
+            Ok(Some(client.address.clone()))
+        }
+        Decision::FocusLast => hypr.dispatch("focuscurrentorlast", "").map(|()| None),
+        Decision::Minimize(client) => {
+            minimize_window(hypr, config, client, clients, !args.plan).map(|()| None)
+        }
+        Decision::Launch(launch) => {
+            if config.join_group {
+                focus_group(hypr, config, candidates)?;
+            }
+            let matches = |client: &Client| filter.matches(client);
+            launch_window(hypr, config, matches, launch, clients)
+        }
+        Decision::Nothing => Ok(None),
+    }
+}
+
+/// Send --send-keys and switch submaps once the window raise focused has focus
+fn follow_up(
+    run: Invocation<impl Compositor>,
+    keys: Option<&Keys>,
+    address: &str,
+    timings: &mut Timings,
+) -> Result<()> {
+    let Invocation { hypr, config, .. } = run;
+    if let Some(keys) = keys {
+        Timings::measure(&mut timings.dispatch, || {
+            send_keys(hypr, config, keys, address)
+        })?;
+    }
+    let submap = config
+        .enter_submap
+        .as_deref()
+        .or(config.reset_submap.then_some("reset"));
+    if let Some(submap) = submap {
+        Timings::measure(&mut timings.dispatch, || hypr.dispatch("submap", submap))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raise::version::Version;
+    use std::cell::{Cell, RefCell};
+
+    fn client(class: &str, address: &str, pinned: bool) -> Client {
+        let json = format!(
+            r#"{{"class": "{class}", "title": "", "address": "{address}", "pid": 0, "monitor": 0,
+                "workspace": {{"id": 1, "name": "1"}}, "pinned": {pinned}, "xwayland": false,
+                "floating": false}}"#
+        );
+        miniserde::json::from_str(&json).unwrap()
+    }
+
+    /// Serves clients and records dispatches instead of sending them, where `exec` opens
+    /// the launched windows
+    struct Fake {
+        clients: RefCell<Vec<Client>>,
+        launched: Vec<Client>,
+        active: RefCell<Option<String>>,
+        dispatches: RefCell<Vec<String>>,
+        /// A window that takes focus whenever another one is focused, like a modal dialog
+        modal: Option<&'static str>,
+        /// How many more times listing clients fails
+        failures: Cell<usize>,
+        /// Menu commands shown, which go on to run
+        menus: RefCell<Vec<String>>,
+    }
+
+    impl Compositor for Fake {
+        fn check_instance(&self) -> raise::Result<()> {
+            Ok(())
+        }
+
+        fn clients(&self) -> raise::Result<Vec<Client>> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(std::io::Error::other("hyprctl crashed").into());
+            }
+            Ok(self.clients.borrow().clone())
+        }
+
+        fn active_window(&self) -> raise::Result<Client> {
+            let clients = self.clients.borrow();
+            let active = self.active.borrow();
+            let active = clients
+                .iter()
+                .find(|client| Some(&client.address) == active.as_ref());
+            active.cloned().ok_or(RaiseError::NoMatch)
+        }
+
+        fn version(&self) -> raise::Result<Version> {
+            let json = r#"{"tag": "v0.35.0", "commit": "0000000"}"#;
+            Ok(miniserde::json::from_str(json).unwrap())
+        }
+
+        fn client_values(&self) -> raise::Result<Vec<Value>> {
+            let json = miniserde::json::to_string(&*self.clients.borrow());
+            Ok(miniserde::json::from_str(&json).unwrap())
+        }
+
+        fn dispatch(&self, dispatcher: &str, argument: &str) -> raise::Result<()> {
+            self.apply(dispatcher, argument);
+            let dispatch = format!("{dispatcher} {argument}").trim_end().to_string();
+            self.dispatches.borrow_mut().push(dispatch);
+            Ok(())
+        }
+
+        fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> raise::Result<()> {
+            for (dispatcher, argument) in dispatches {
+                self.apply(dispatcher, argument);
+            }
+            let batch = dispatches
+                .iter()
+                .map(|(dispatcher, argument)| format!("{dispatcher} {argument}"))
+                .map(|dispatch| dispatch.trim_end().to_string())
+                .collect::<Vec<_>>()
+                .join(" ; ");
+            self.dispatches.borrow_mut().push(format!("batch {batch}"));
+            Ok(())
+        }
+
+        fn choose<'a>(
+            &self,
+            command: &str,
+            clients: &[&'a Client],
+        ) -> raise::Result<Option<&'a Client>> {
+            self.menus.borrow_mut().push(command.into());
+            raise::menu::choose(command, clients)
+        }
+    }
+
+    impl Fake {
+        /// The effects of the dispatches later steps depend on
+        fn apply(&self, dispatcher: &str, argument: &str) {
+            match dispatcher {
+                "exec" => self
+                    .clients
+                    .borrow_mut()
+                    .extend(self.launched.iter().cloned()),
+                "focuswindow" => {
+                    let address = argument.strip_prefix("address:");
+                    *self.active.borrow_mut() = self.modal.or(address).map(Into::into)
+                }
+                "closewindow" => self
+                    .clients
+                    .borrow_mut()
+                    .retain(|client| argument != format!("address:{}", client.address)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Arguments, active window, then the expected dispatches or error
+    type Case = (
+        &'static [&'static str],
+        Option<&'static str>,
+        Result<&'static [&'static str], &'static str>,
+    );
+
+    /// Run every case, comparing the dispatches or error with the expected ones
+    fn check(cases: &[Case]) {
+        for (args, active, expected) in cases {
+            let expected = expected
+                .map(|dispatches| {
+                    dispatches
+                        .iter()
+                        .map(|dispatch| dispatch.to_string())
+                        .collect()
+                })
+                .map_err(str::to_string);
+            assert_eq!(raise(args, *active), expected, "raise {}", args.join(" "));
+        }
+    }
+
+    /// The usual windows, with the given one focused
+    fn fake(active: Option<&'static str>) -> Fake {
+        Fake {
+            clients: RefCell::new(vec![
+                Client {
+                    grouped: Some(vec!["0x1".into(), "0x3".into()]),
+                    focus_history_id: Some(1),
+                    ..client("kitty", "0x1", false)
+                },
+                Client {
+                    at: Some(vec![100, 50]),
+                    size: Some(vec![800, 600]),
+                    fullscreen: Some(raise::Fullscreen(2)),
+                    ..client("firefox", "0x2", false)
+                },
+                Client {
+                    xwayland: true,
+                    floating: true,
+                    grouped: Some(vec!["0x1".into(), "0x3".into()]),
+                    focus_history_id: Some(0),
+                    ..client("kitty", "0x3", false)
+                },
+                client("mpv", "0x4", true),
+                Client {
+                    workspace: raise::Workspace {
+                        id: -98,
+                        name: "special:scratch".into(),
+                    },
+                    ..client("btop", "0x7", false)
+                },
+            ]),
+            // A splash screen, followed by the main window
+            launched: vec![
+                client("foot", "0x5", false),
+                client("foot-main", "0x6", false),
+            ],
+            active: RefCell::new(active.map(Into::into)),
+            dispatches: RefCell::default(),
+            modal: None,
+            failures: Cell::new(0),
+            menus: RefCell::default(),
+        }
+    }
+
+    /// An empty directory for the state files of one test, given with --state-dir
+    fn empty_state_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("raise-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Run raise against a fake with the given arguments
+    fn raise_with(fake: &impl Compositor, args: &[&str]) -> Result<(), String> {
+        let args = Args::from_args(&["raise"], args).map_err(|exit| exit.output)?;
+        let file = <ConfigFile as Default>::default();
+        let layer = args.layer().map_err(|error| error.to_string())?;
+        let config = RaiseConfig::merge(&file, None, layer).unwrap();
+        run(fake, &args, &config, &mut Timings::default()).map_err(|error| error.to_string())
+    }
+
+    /// Run raise with the given arguments, returning the dispatches or the error
+    fn raise(args: &[&str], active: Option<&'static str>) -> Result<Vec<String>, String> {
+        let fake = fake(active);
+        raise_with(&fake, args)?;
+        Ok(fake.dispatches.into_inner())
+    }
+
+    #[test]
+    fn all_instances_act_where_the_window_is() {
+        let other = Fake {
+            clients: RefCell::new(vec![
+                client("kitty", "0x8", false),
+                client("obs", "0x9", false),
+            ]),
+            ..fake(None)
+        };
+        let all = AllInstances::new(vec![fake(None), other]);
+        for args in [
+            &["-c", "obs"][..],
+            &["-c", "kitty"],
+            &["-c", "nope", "-e", "nope"],
+        ] {
+            raise_with(&all, args).unwrap();
+        }
+        let [current, other] = <[Fake; 2]>::try_from(all.into_instances()).ok().unwrap();
+        // Windows of the current instance come first, and launching happens there
+        let current = current.dispatches.into_inner();
+        assert_eq!(current, ["focuswindow address:0x1", "exec nope"]);
+        assert_eq!(other.dispatches.into_inner(), ["focuswindow address:0x9"]);
+    }
+
+    #[test]
+    fn all_instances_split_batches_by_instance() {
+        let other = Fake {
+            clients: RefCell::new(vec![client("obs", "0x9", false)]),
+            ..fake(None)
+        };
+        let all = AllInstances::new(vec![fake(None), other]);
+        all.clients().unwrap();
+        let batch = [
+            ("alterzorder", "top,address:0x1".to_string()),
+            ("alterzorder", "top,address:0x9".to_string()),
+            ("centerwindow", String::new()),
+            ("alterzorder", "top,address:0x2".to_string()),
+        ];
+        all.dispatch_batch(&batch).unwrap();
+        let [current, other] = <[Fake; 2]>::try_from(all.into_instances()).ok().unwrap();
+        // Dispatches about no window follow the one before
+        assert_eq!(
+            current.dispatches.into_inner(),
+            [
+                "batch alterzorder top,address:0x1",
+                "batch alterzorder top,address:0x2"
+            ]
+        );
+        assert_eq!(
+            other.dispatches.into_inner(),
+            ["batch alterzorder top,address:0x9 ; centerwindow"]
+        );
+    }
+
+    #[test]
+    fn wrappers_forward_programs_and_keys() {
+        let fake = fake(None);
+        let classes = BTreeMap::new();
+        let hypr = BestEffort {
+            inner: ClassMap {
+                inner: AllInstances::new(vec![Planner::new(&fake)]),
+                classes,
+            },
+            verbose: false,
+        };
+        let argv = ["true".to_string()];
+        let program = Spawn {
+            argv: &argv,
+            detach: false,
+            log: None,
+            cwd: None,
+        };
+        hypr.spawn(&program).unwrap();
+        hypr.type_keys(&"ctrl+l".parse().unwrap()).unwrap();
+        let planner = hypr.inner.inner.into_instances().remove(0);
+        assert_eq!(
+            planner.ops(),
+            [
+                Op::Spawn {
+                    argv: argv.to_vec(),
+                    cwd: None
+                },
+                Op::Type {
+                    keys: "ctrl+l".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cached_clients_are_kept_per_instance_as_reported() {
+        let dir = empty_state_dir("cache");
+        let cached = |inner, signature: &str| Cached {
+            inner,
+            dir: dir.clone(),
+            signature: Some(signature.into()),
+            ttl: Some(60_000),
+        };
+        let classes = BTreeMap::from([("firefox".to_string(), "browser".to_string())]);
+        let one = ClassMap {
+            inner: cached(fake(None), "one"),
+            classes,
+        };
+        let other = Fake {
+            clients: RefCell::new(vec![client("obs", "0x9", false)]),
+            ..fake(None)
+        };
+        let two = cached(other, "two");
+        let classes = |clients: Vec<Client>| {
+            let classes = clients.into_iter().map(|client| client.class);
+            classes.collect::<Vec<_>>()
+        };
+        let mapped = ["kitty", "browser", "kitty", "mpv", "btop"];
+        assert_eq!(classes(one.clients().unwrap()), mapped);
+        // The other instance has a cache of its own, rather than the clients of the first
+        assert_eq!(classes(two.clients().unwrap()), ["obs"]);
+        // Later queries are served from the cache, which holds the classes as reported
+        one.inner.inner.clients.borrow_mut().clear();
+        assert_eq!(classes(one.clients().unwrap()), mapped);
+        let reported = cache::load(&dir, "one", 60_000).unwrap();
+        assert_eq!(
+            classes(reported),
+            ["kitty", "firefox", "kitty", "mpv", "btop"]
+        );
+        // Until a dispatch drops it
+        one.dispatch("focuswindow", "address:0x1").unwrap();
+        assert!(one.clients().unwrap().is_empty());
+        two.inner.clients.borrow_mut().clear();
+        assert_eq!(classes(two.clients().unwrap()), ["obs"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn class_map_applies_to_the_active_window_too() {
+        let classes = BTreeMap::from([("firefox".to_string(), "kitty".to_string())]);
+        // The focused firefox window counts as a kitty one, so cycling moves on from it
+        for args in [&["-c", "kitty"][..], &["--current-class"]] {
+            let hypr = ClassMap {
+                inner: fake(Some("0x2")),
+                classes: classes.clone(),
+            };
+            raise_with(&hypr, args).unwrap();
+            assert_eq!(
+                hypr.inner.dispatches.into_inner(),
+                ["focuswindow address:0x3"]
+            );
+        }
+    }
+
+    #[test]
+    fn toggle_special_finds_windows_by_their_initial_class() {
+        let fake = Fake {
+            clients: RefCell::new(vec![Client {
+                initial_class: Some("electron".into()),
+                ..client("discord", "0x8", false)
+            }]),
+            ..fake(None)
+        };
+        let args = [
+            "-c",
+            "electron",
+            "-e",
+            "discord",
+            "--toggle-special",
+            "chat",
+        ];
+        raise_with(&fake, &[&args[..], &["--class-or-initial"]].concat()).unwrap();
+        assert_eq!(fake.dispatches.take(), ["togglespecialworkspace chat"]);
+        // Going by the class alone, it is not running yet
+        raise_with(&fake, &args).unwrap();
+        assert_eq!(
+            fake.dispatches.take(),
+            [
+                "exec [workspace special:chat silent] discord",
+                "togglespecialworkspace chat"
+            ]
+        );
+    }
+
+    #[test]
+    fn wait_for_compares_the_class_like_class_does() {
+        let launched = |main: Client| Fake {
+            launched: vec![client("splash", "0x5", false), main],
+            ..fake(None)
+        };
+        let args = ["-c", "foot", "-e", "foot", "--wait", "100", "--wait-for"];
+        let focused = |fake: &Fake| fake.dispatches.borrow().last().cloned();
+        let focus = Some("focuswindow address:0x6".to_string());
+
+        let fake = launched(client("Cafe\u{301}", "0x6", false));
+        raise_with(&fake, &[&args[..], &["Caf\u{e9}", "--normalize"]].concat()).unwrap();
+        assert_eq!(focused(&fake), focus);
+
+        let fake = launched(Client {
+            initial_class: Some("electron".into()),
+            ..client("discord", "0x6", false)
+        });
+        raise_with(
+            &fake,
+            &[&args[..], &["electron", "--class-or-initial"]].concat(),
+        )
+        .unwrap();
+        assert_eq!(focused(&fake), focus);
+
+        let hypr = ClassMap {
+            inner: launched(client("foot-main", "0x6", false)),
+            classes: BTreeMap::from([("foot-main".to_string(), "terminal".to_string())]),
+        };
+        raise_with(&hypr, &[&args[..], &["terminal"]].concat()).unwrap();
+        assert_eq!(focused(&hypr.inner), focus);
+    }
+
+    #[test]
+    fn strict_refuses_to_launch_blindly() {
+        let fake = Fake {
+            failures: Cell::new(usize::MAX),
+            ..fake(None)
+        };
+        assert_eq!(raise_with(&fake, &["-c", "kitty", "-e", "kitty"]), Ok(()));
+        assert_eq!(fake.dispatches.take(), ["exec kitty"]);
+        assert_eq!(
+            raise_with(&fake, &["-c", "kitty", "-e", "kitty", "--strict"]),
+            Err("Could not list windows, --strict refuses to act without them".into())
+        );
+        assert!(fake.dispatches.take().is_empty());
+    }
+
+    #[test]
+    fn verify_focus_fails_when_focus_is_intercepted() {
+        let fake = Fake {
+            modal: Some("0x4"),
+            ..fake(None)
+        };
+        assert_eq!(
+            raise_with(&fake, &["-c", "firefox", "--verify-focus"]),
+            Err("Focusing 0x2 left 0x4 focused".into())
+        );
+        assert_eq!(
+            fake.dispatches.into_inner(),
+            ["focuswindow address:0x2", "focuswindow address:0x2"]
+        );
+    }
+
+    #[test]
+    fn move_to_current_skips_pinned_windows() {
+        let config = RaiseConfig {
+            move_to_current: true,
+            ..RaiseConfig::default()
+        };
+
+        let unpinned = client("mpv", "0x1", false);
+        let dispatches = prepare_dispatches(&config, &unpinned.address, Some(&unpinned)).unwrap();
+        assert_eq!(
+            dispatches,
+            [("movetoworkspace", "+0,address:0x1".to_string())]
+        );
+
+        let pinned = client("mpv", "0x2", true);
+        let dispatches = prepare_dispatches(&config, &pinned.address, Some(&pinned)).unwrap();
+        assert!(dispatches.is_empty());
+    }
+
+    #[test]
+    fn cycling_goes_by_where_focus_is() {
+        // The only candidate, when it is focused, behind another window or with nothing focused
+        for active in [Some("0x4"), Some("0x2"), None] {
+            assert_eq!(
+                raise(&["-c", "mpv"], active),
+                Ok(vec!["focuswindow address:0x4".into()])
+            );
+        }
+        // Of several, the next one after a focused candidate and the first one otherwise
+        let kitty = |active| raise(&["-c", "kitty"], active).unwrap();
+        assert_eq!(kitty(Some("0x1")), ["focuswindow address:0x3"]);
+        assert_eq!(kitty(Some("0x2")), ["focuswindow address:0x1"]);
+        assert_eq!(kitty(None), ["focuswindow address:0x1"]);
+        // No candidate launches whatever is focused
+        for active in [Some("0x1"), Some("0x7"), None] {
+            assert_eq!(
+                raise(&["-c", "foot", "-e", "foot"], active),
+                Ok(vec!["exec foot".into()])
+            );
+        }
+    }
+
+    #[test]
+    fn explanation_names_the_candidate() {
+        let config = RaiseConfig::default();
+        let (first, second) = (client("kitty", "0x1", false), client("kitty", "0x3", false));
+        let candidates = [&first, &second];
+        let explanation = Explanation {
+            config: &config,
+            criteria: "class 'kitty'".into(),
+            menu: false,
+            candidates: &candidates,
+        };
+        assert_eq!(
+            explanation.sentence(&Decision::Focus(&second), Some(&first)),
+            "Active window 0x1 matches class 'kitty' and is candidate #1 of 2; \
+             focusing next candidate #2, 0x3 on workspace 1."
+        );
+        assert_eq!(
+            explanation.sentence(&Decision::Focus(&first), None),
+            "The active window does not match class 'kitty', 2 do; \
+             focusing first candidate #1, 0x1 on workspace 1."
+        );
+    }
+
+    #[test]
+    fn newest_is_the_last_listed() {
+        let (first, second) = (client("kitty", "0x1", false), client("kitty", "0x2", false));
+        assert_eq!(Client::newest(&[&first, &second]).unwrap().address, "0x2");
+        assert!(Client::newest(&[]).is_none());
+    }
+
+    #[test]
+    fn decision_matrix() {
+        #[rustfmt::skip]
+        let cases: &[Case] = &[
+            // No candidates
+            (&["-c", "foot", "-e", "foot"], None, Ok(&["exec foot"])),
+            (&["-c", "foot", "-e", "foot"], Some("0x1"), Ok(&["exec foot"])),
+            (&["-c", "foot", "-e", "foot", "--launcher", "hyprctl"], None, Ok(&["exec foot"])),
+            (&["-c", "foot", "-e", "foot", "--cwd", "/"], None, Ok(&["exec cd '/' && foot"])),
+            (&["-c", "foot", "-e", "foot", "--cwd", "/nonexistent"], None,
+                Err("Invalid arguments: --cwd /nonexistent is not a directory")),
+            (&["-c", "foot"], None, Ok(&[])),
+            (&["-c", "foot", "-e", "foot", "--require-launch"], None, Ok(&["exec foot"])),
+            (&["-c", "foot", "-e", "foot", "--launch-and-wait-for-focus"], None, Ok(&[
+                "exec foot",
+                "focuswindow address:0x5",
+            ])),
+            (&["-c", "foot", "-e", "foot", "--launch-and-wait-for-focus", "--focus-method",
+                "raise"], None, Ok(&[
+                    "exec foot",
+                    "batch alterzorder top,address:0x5 ; focuswindow address:0x5",
+                ])),
+            (&["-c", "bar", "-e", "bar", "--require-launch", "--wait", "0"], None,
+                Err("`bar` opened no matching window within 0ms")),
+            (&["-c", "foot", "-e", "foot", "--after-launch-focus"], None, Ok(&[
+                "exec foot",
+                "focuswindow address:0x5",
+            ])),
+            // Keys go to the window raise focused, and only to that one
+            (&["-c", "firefox", "--send-keys", "ctrl+l"], None, Ok(&[
+                "focuswindow address:0x2",
+                "sendshortcut CTRL, l, address:0x2",
+            ])),
+            (&["-c", "foot", "-e", "foot", "--after-launch-focus", "--send-keys", "Ctrl+Shift+t"],
+                None, Ok(&[
+                "exec foot",
+                "focuswindow address:0x5",
+                "sendshortcut CTRL SHIFT, t, address:0x5",
+            ])),
+            (&["-c", "foot", "-e", "foot", "--send-keys", "ctrl+l"], None, Ok(&["exec foot"])),
+            (&["-c", "firefox", "--send-keys", "ctrl+"], None, Err(
+                "Invalid arguments: `ctrl+` is not a key combination, expected like `ctrl+l`",
+            )),
+            // Submaps switch once the window is focused, after any keys
+            (&["-c", "firefox", "--send-keys", "ctrl+l", "--enter-submap", "web"], None, Ok(&[
+                "focuswindow address:0x2",
+                "sendshortcut CTRL, l, address:0x2",
+                "submap web",
+            ])),
+            (&["-c", "firefox", "--reset-submap"], None, Ok(&[
+                "focuswindow address:0x2",
+                "submap reset",
+            ])),
+            (&["-c", "foot", "-e", "foot", "--enter-submap", "term"], None, Ok(&["exec foot"])),
+            (&["-c", "firefox", "--enter-submap", "web", "--reset-submap"], None, Err(
+                "Invalid arguments: --enter-submap and --reset-submap are mutually exclusive",
+            )),
+            (&["-c", "foot", "-e", "foot", "--wait-for", "foot-main"], None, Ok(&[
+                "exec foot",
+                "focuswindow address:0x6",
+            ])),
+            (&["-c", "foot", "-e", "foot", "--no-launch"], None, Ok(&[])),
+            (&["-c", "foot", "-e", "foot", "--move-to-current"], None, Ok(&["exec foot"])),
+            // One candidate
+            (&["-c", "firefox", "-e", "firefox"], None, Ok(&["focuswindow address:0x2"])),
+            (&["-c", "firefox", "-e", "firefox"], Some("0x1"), Ok(&["focuswindow address:0x2"])),
+            (&["-c", "firefox", "-e", "firefox"], Some("0x2"), Ok(&["focuswindow address:0x2"])),
+            (&["-c", "firefox", "--toggle-last"], Some("0x2"), Ok(&["focuscurrentorlast"])),
+            // Several candidates cycle from the active one
+            (&["-c", "kitty"], None, Ok(&["focuswindow address:0x1"])),
+            (&["-c", "kitty"], Some("0x1"), Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty"], Some("0x3"), Ok(&["focuswindow address:0x1"])),
+            (&["--current-class"], Some("0x1"), Ok(&["focuswindow address:0x3"])),
+            (&["--current-class"], Some("0x2"), Ok(&["focuswindow address:0x2"])),
+            (&["--current-class"], None, Ok(&[])),
+            (&["-c", "kitty", "--current-class"], Some("0x1"),
+                Err("Invalid arguments: --class and --current-class are mutually exclusive")),
+            (&["-c", "firefox", "--verify-focus"], None, Ok(&["focuswindow address:0x2"])),
+            // The cursor only moves for windows with a known geometry
+            (&["-c", "firefox", "--cursor-center"], None, Ok(&[
+                "focuswindow address:0x2",
+                "movecursor 500 350",
+            ])),
+            (&["-c", "kitty", "--cursor-center"], None, Ok(&["focuswindow address:0x1"])),
+            // Cycling order
+            (&["-c", "kitty", "--order", "reverse"], None, Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--order", "reverse"], Some("0x3"), Ok(&["focuswindow address:0x1"])),
+            (&["-c", "kitty", "--order", "reverse", "--index", "2"], None,
+                Ok(&["focuswindow address:0x1"])),
+            (&["-c", "kitty", "--order", "mru"], None, Ok(&["focuswindow address:0x3"])),
+            (&["--workspace-pattern", "*", "--order", "workspace"], None,
+                Ok(&["focuswindow address:0x7"])),
+            (&["--workspace-pattern", "*", "--order", "address", "--index", "5"], None,
+                Ok(&["focuswindow address:0x7"])),
+            (&["--workspace-pattern", "*", "--order", "shuffle", "--seed", "1"], None,
+                Ok(&["focuswindow address:0x3"])),
+            // Workspace patterns are regexes after `re:`, checked before anything else
+            (&["--workspace-pattern", "re:^special:"], None, Ok(&["focuswindow address:0x7"])),
+            (&["-c", "kitty", "--workspace-pattern", r"re:^\d+$"], None,
+                Ok(&["focuswindow address:0x1"])),
+            (&["-c", "kitty", "--workspace-pattern", "re:^special:"], None, Ok(&[])),
+            (&["-c", "kitty", "--workspace-pattern", "re:(x", "--address", "0x1"], None,
+                Err("Invalid arguments: pattern `(x` is not a regex: found open group without \
+                     closing ')'")),
+            // Newest goes by the order windows are listed in, which --order leaves alone
+            (&["-c", "kitty", "--order", "reverse", "--newest"], None,
+                Ok(&["focuswindow address:0x3"])),
+            // Arbitrary fields
+            (&["-c", "kitty", "--match-json", "xwayland=true"], None,
+                Ok(&["focuswindow address:0x3"])),
+            (&["--classless", "--match-json", "class=kitty"], None, Ok(&[])),
+            (&["--match-json", "workspace.name=1", "--match-json", "pinned=true", "-c", "mpv"],
+                None, Ok(&["focuswindow address:0x4"])),
+            (&["-c", "kitty", "--match-json", "workspace.id=2"], None, Ok(&[])),
+            (&["-c", "kitty", "--match-json", "xwayland"], None, Err(
+                "Invalid arguments: `xwayland` is not a condition, expected `field=value`",
+            )),
+            (&["-c", "kitty", "--index", "2"], Some("0x3"), Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--index", "3", "-e", "kitty"], None, Ok(&["exec kitty"])),
+            (&["-c", "kitty", "--index", "3", "--no-launch"], None,
+                Err("No window at --index 3, only 2 match")),
+            // The menu picks between several windows, where picking nothing does nothing
+            (&["-c", "kitty", "--menu", "tail -n 1"], None, Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--menu", "head -n 1"], Some("0x1"),
+                Ok(&["focuswindow address:0x1"])),
+            (&["-c", "kitty", "--menu", "true"], None, Ok(&[])),
+            (&["-c", "kitty", "--menu", "cat; exit 1"], None, Ok(&[])),
+            (&["-c", "kitty", "--menu", "echo kitty"], None, Ok(&[])),
+            (&["-c", "firefox", "--menu", "false"], None, Ok(&["focuswindow address:0x2"])),
+            // The last window listed is the newest
+            (&["-c", "kitty", "--newest"], Some("0x3"), Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--newest", "--close"], None, Ok(&["closewindow address:0x3"])),
+            (&["-c", "nope", "--newest", "-e", "nope"], None, Ok(&["exec nope"])),
+            (&["-c", "kitty", "--newest", "--index", "1"], None,
+                Err("Invalid arguments: --newest and --index are mutually exclusive")),
+            (&["-c", "kitty", "--raise-all"], Some("0x1"), Ok(&[
+                "batch alterzorder top,address:0x1 ; alterzorder top,address:0x3",
+                "focuswindow address:0x3",
+            ])),
+            (&["-c", "kitty", "--focus-method", "raise"], None, Ok(&[
+                "batch alterzorder top,address:0x1 ; focuswindow address:0x1",
+            ])),
+            // Moving
+            (&["-c", "kitty", "--move-to-current"], None, Ok(&[
+                "batch movetoworkspace +0,address:0x1 ; focuswindow address:0x1",
+            ])),
+            // Warming up launches hidden, and only once
+            (&["-c", "kitty", "-e", "kitty", "--launch-hidden", "term"], None, Ok(&[])),
+            (&["-c", "nope", "-e", "nope", "--launch-hidden", "term"], None, Ok(&[
+                "exec [workspace special:term silent] nope",
+            ])),
+            (&["-c", "nope", "--launch-hidden", "term"], None,
+                Err("Invalid arguments: --launch-hidden requires `--launch`")),
+            (&["-c", "nope", "-e", "nope", "--launch-hidden", "a", "--toggle-special", "a"], None,
+                Err(concat!(
+                    "Invalid arguments: ",
+                    "--launch-hidden and --toggle-special are mutually exclusive",
+                ))),
+            // Explaining reads but does not act
+            (&["-c", "kitty", "--explain"], Some("0x1"), Ok(&[])),
+            (&["-c", "nope", "-e", "nope", "--explain"], None, Ok(&[])),
+            (&["-c", "kitty", "--close", "--explain"], None, Ok(&[])),
+            (&["-c", "kitty", "-e", "kitty", "--toggle-special", "term", "--explain"], None,
+                Ok(&[])),
+            // Gathering moves every matching window over
+            // One policy for each number of matching windows
+            (&["-c", "foot", "-e", "foot", "--on-none", "nothing"], None, Ok(&[])),
+            (&["-c", "foot", "-e", "foot", "--on-none", "launch"], None, Ok(&["exec foot"])),
+            (&["-c", "foot", "--on-none", "launch"], None, Err(
+                "Invalid arguments: --on-none launch requires `--launch` or a command after `--`",
+            )),
+            (&["-c", "mpv", "--on-one", "toggle"], Some("0x4"), Ok(&["focuscurrentorlast"])),
+            (&["-c", "mpv", "--on-one", "toggle"], Some("0x1"), Ok(&["focuswindow address:0x4"])),
+            (&["-c", "mpv", "--on-one", "focus", "--toggle-last"], Some("0x4"),
+                Ok(&["focuswindow address:0x4"])),
+            (&["-c", "kitty", "--on-one", "toggle"], Some("0x1"), Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--on-many", "menu", "--menu", "tail -n 1"], None,
+                Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--on-many", "cycle", "--menu", "tail -n 1"], Some("0x1"),
+                Ok(&["focuswindow address:0x3"])),
+            (&["-c", "mpv", "--on-many", "gather"], None, Ok(&["focuswindow address:0x4"])),
+            (&["-c", "kitty", "--on-many", "gather", "--move-relative", "1"], None, Err(
+                "Invalid arguments: --on-many gather and --move-relative are mutually exclusive",
+            )),
+            (&["-c", "kitty", "--on-many", "menu", "--index", "1"], None, Err(concat!(
+                "Invalid arguments: --on-one and --on-many are mutually exclusive with --index ",
+                "and --newest",
+            ))),
+            (&["-c", "kitty", "--on-many", "gather"], None, Ok(&[
+                "movetoworkspace +0,address:0x3",
+                "batch movetoworkspace +0,address:0x1 ; focuswindow address:0x1",
+            ])),
+            (&["-c", "kitty", "--gather"], None, Ok(&[
+                "movetoworkspace +0,address:0x3",
+                "batch movetoworkspace +0,address:0x1 ; focuswindow address:0x1",
+            ])),
+            (&["-c", "kitty", "--gather", "--keep-focus", "--silent-move"], Some("0x2"), Ok(&[
+                "movetoworkspacesilent +0,address:0x3",
+                "movetoworkspacesilent +0,address:0x1",
+                "focuswindow address:0x2",
+            ])),
+            (&["-c", "kitty", "--keep-focus"], None,
+                Err("Invalid arguments: --keep-focus requires --gather")),
+            (&["-c", "kitty", "--gather", "--move-relative", "1"], None,
+                Err("Invalid arguments: --gather and --move-relative are mutually exclusive")),
+            // Scratchpad windows are summoned to the current workspace
+            (&["--only-special"], None, Ok(&[
+                "batch movetoworkspace +0,address:0x7 ; focuswindow address:0x7",
+            ])),
+            (&["-c", "btop", "--only-special", "--move-relative", "2"], None, Ok(&[
+                "batch movetoworkspace +2,address:0x7 ; focuswindow address:0x7",
+            ])),
+            (&["-c", "kitty", "--only-special", "--no-launch"], None, Ok(&[])),
+            (&["-c", "kitty", "--move-to-current", "--silent-move"], Some("0x1"), Ok(&[
+                "batch movetoworkspacesilent +0,address:0x3 ; focuswindow address:0x3",
+            ])),
+            (&["-c", "kitty", "--move-to-current", "--focus-monitor"], None, Ok(&[
+                "batch movetoworkspace +0,address:0x1 ; focuswindow address:0x1",
+            ])),
+            (&["-c", "kitty", "--focus-monitor"], None, Ok(&[
+                "batch focusmonitor 0 ; focuswindow address:0x1",
+            ])),
+            (&["-c", "kitty", "--focus-monitor", "--focus-method", "raise"], None, Ok(&[concat!(
+                "batch focusmonitor 0 ; alterzorder top,address:0x1 ; ",
+                "focuswindow address:0x1",
+            )])),
+            (&["-c", "kitty", "--move-relative", "+1"], None, Ok(&[
+                "batch movetoworkspace +1,address:0x1 ; focuswindow address:0x1",
+            ])),
+            (&["-c", "kitty", "--move-relative", "-2"], None, Ok(&[
+                "batch movetoworkspace -2,address:0x1 ; focuswindow address:0x1",
+            ])),
+            (&["-c", "kitty", "--move-relative", "3", "--silent-move"], None, Ok(&[
+                "batch movetoworkspacesilent +3,address:0x1 ; focuswindow address:0x1",
+            ])),
+            // Only launched windows move with --on-launch-only-move
+            (&["-c", "kitty", "--move-relative", "+1", "--on-launch-only-move"], None, Ok(&[
+                "focuswindow address:0x1",
+            ])),
+            (&["-c", "foot", "-e", "foot", "--move-relative", "+1", "--on-launch-only-move"],
+                None, Ok(&["exec foot", "movetoworkspace +1,address:0x5"])),
+            (&["-c", "kitty", "--on-launch-only-move"], None, Err(concat!(
+                "Invalid arguments: --on-launch-only-move requires --move-to-current, ",
+                "--move-relative or --move-to-nearest-empty",
+            ))),
+            // Fullscreen windows leave fullscreen to move, and can get it back once focused
+            (&["-c", "firefox", "--move-to-current"], None, Ok(&[concat!(
+                "batch focuswindow address:0x2 ; fullscreen 0 ; ",
+                "movetoworkspace +0,address:0x2 ; focuswindow address:0x2",
+            )])),
+            (&["-c", "firefox", "--move-relative", "1", "--restore-fullscreen"], None, Ok(&[
+                concat!(
+                    "batch focuswindow address:0x2 ; fullscreen 0 ; ",
+                    "movetoworkspace +1,address:0x2 ; focuswindow address:0x2 ; fullscreen 0",
+                ),
+            ])),
+            (&["-c", "firefox", "--restore-fullscreen"], None, Ok(&["focuswindow address:0x2"])),
+            (&["-c", "kitty", "--move-relative", "+x"], None, Err(concat!(
+                "Error parsing option '--move-relative' with value '+x': ",
+                "expected an offset like `+1` or `-2`, got `+x`\n",
+            ))),
+            (&["-c", "kitty", "--move-relative", "+1", "--move-to-current"], None, Err(
+                "Invalid arguments: --move-to-current and --move-relative are mutually exclusive",
+            )),
+            (&["-c", "kitty", "--move-to-nearest-empty"], None, Ok(&[
+                "batch movetoworkspace empty,address:0x1 ; focuswindow address:0x1",
+            ])),
+            (&["-c", "kitty", "--move-to-nearest-empty", "--empty-on-monitor"], None, Ok(&[
+                "batch movetoworkspace emptym,address:0x1 ; focuswindow address:0x1",
+            ])),
+            (&["-c", "kitty", "--empty-on-monitor"], None,
+                Err("Invalid arguments: --empty-on-monitor requires --move-to-nearest-empty")),
+            (&["-c", "kitty", "--move-to-current", "--move-to-nearest-empty"], None, Err(concat!(
+                "Invalid arguments: --move-to-current and --move-to-nearest-empty ",
+                "are mutually exclusive",
+            ))),
+            (&["-c", "kitty", "--move-to-current", "--no-batch"], None, Ok(&[
+                "movetoworkspace +0,address:0x1",
+                "focuswindow address:0x1",
+            ])),
+            (&["-c", "mpv", "--move-to-current"], None, Ok(&["focuswindow address:0x4"])),
+            (&["-c", "kitty", "--move-to-current", "--move-dispatcher", "exec"], None,
+                Err(concat!("Invalid arguments: unknown move dispatcher `exec`, ",
+                    "use movetoworkspace, movetoworkspacesilent"))),
+            // Closing
+            (&["-c", "kitty", "--close"], Some("0x3"), Ok(&["closewindow address:0x3"])),
+            (&["-c", "kitty", "--close"], Some("0x2"), Ok(&["closewindow address:0x1"])),
+            (&["-c", "kitty", "--close", "--index", "2"], None, Ok(&["closewindow address:0x3"])),
+            (&["-c", "kitty", "--grace-close", "100"], None, Ok(&["closewindow address:0x1"])),
+            (&["-c", "foot", "-e", "foot", "--close"], None, Ok(&[])),
+            // Forced launches
+            (&["-c", "kitty", "-e", "kitty", "--force-launch"], Some("0x1"), Ok(&["exec kitty"])),
+            (&["-c", "foot", "-e", "foot", "--force-launch"], None, Ok(&["exec foot"])),
+            (&["-c", "kitty", "-e", "kitty", "--force-launch", "--join-group"], None, Ok(&[
+                "focuswindow address:0x1",
+                "exec kitty",
+            ])),
+            (&["-c", "firefox", "-e", "firefox", "--force-launch", "--join-group"], None,
+                Ok(&["exec firefox"])),
+            (&["-c", "kitty", "--force-launch"], None,
+                Err(concat!("Invalid arguments: --force-launch requires `--launch` ",
+                    "or a command after `--`"))),
+            (&["-c", "kitty", "-e", "kitty", "--force-launch", "--no-launch"], None,
+                Err("Invalid arguments: --force-launch and --no-launch are mutually exclusive")),
+        ];
+
+        check(cases);
+    }
+
+    #[test]
+    fn xwayland_tells_native_and_xwayland_windows_apart() {
+        #[rustfmt::skip]
+        check(&[
+            (&["-c", "kitty", "--xwayland", "yes"], None, Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--xwayland", "yes"], Some("0x3"), Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--xwayland", "no"], None, Ok(&["focuswindow address:0x1"])),
+            (&["-c", "kitty", "--xwayland", "no"], Some("0x1"), Ok(&["focuswindow address:0x1"])),
+            (&["-c", "kitty", "--xwayland", "maybe"], None, Err(concat!(
+                "Error parsing option '--xwayland' with value 'maybe': ",
+                "expected `yes` or `no`, got `maybe`\n"
+            ))),
+        ]);
+    }
+
+    #[test]
+    fn floating_and_tiled_windows_cycle_apart() {
+        #[rustfmt::skip]
+        check(&[
+            (&["-c", "kitty", "--floating", "yes"], Some("0x1"), Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--floating", "yes"], Some("0x3"), Ok(&["focuswindow address:0x3"])),
+            (&["-c", "kitty", "--floating", "no"], Some("0x1"), Ok(&["focuswindow address:0x1"])),
+            (&["-c", "kitty", "--floating", "no"], Some("0x3"), Ok(&["focuswindow address:0x1"])),
+        ]);
+    }
+
+    #[test]
+    fn explain_leaves_a_known_address_unfocused() {
+        for args in [
+            &["--address", "0x2", "--explain"][..],
+            &["--address", "0x2", "--no-verify", "--explain"],
+        ] {
+            assert_eq!(raise(args, None), Ok(vec![]), "raise {}", args.join(" "));
+        }
+        assert_eq!(
+            raise(&["--address", "0x9", "--explain"], None),
+            Err("No window with address 0x9".into())
+        );
+    }
+
+    #[test]
+    fn wrappers_forward_what_they_leave_alone() {
+        let cached = Cached {
+            inner: fake(None),
+            dir: std::env::temp_dir(),
+            signature: None,
+            ttl: None,
+        };
+        let hypr = BestEffort {
+            inner: ClassMap {
+                inner: cached,
+                classes: BTreeMap::new(),
+            },
+            verbose: false,
+        };
+        raise_with(&hypr, &["-c", "kitty", "--menu", "tail -n 1"]).unwrap();
+        let fake = hypr.inner.inner.inner;
+        assert_eq!(fake.menus.into_inner(), ["tail -n 1"]);
+        assert_eq!(fake.dispatches.into_inner(), ["focuswindow address:0x3"]);
+    }
+
+    #[test]
+    fn explain_says_it_would_prompt_without_prompting() {
+        let fake = fake(None);
+        let menu = ["-c", "kitty", "--menu", "tail -n 1"];
+        raise_with(&fake, &[&menu[..], &["--explain"]].concat()).unwrap();
+        assert!(fake.menus.borrow().is_empty());
+        assert!(fake.dispatches.borrow().is_empty());
+        raise_with(&fake, &menu).unwrap();
+        assert_eq!(fake.menus.into_inner(), ["tail -n 1"]);
+    }
+
+    #[test]
+    fn plan_never_shows_the_menu() {
+        let fake = fake(None);
+        let planner = Planner::new(&fake);
+        raise_with(&planner, &["-c", "kitty", "--menu", "tail -n 1"]).unwrap();
+        assert_eq!(
+            planner.ops(),
+            [Op::Menu {
+                command: "tail -n 1".into(),
+                candidates: 2
+            }]
+        );
+        assert!(fake.menus.into_inner().is_empty());
+    }
+
+    #[test]
+    fn plan_records_without_dispatching() {
+        let focused = fake(Some("0x2"));
+        let planner = Planner::new(&focused);
+        raise_with(
+            &planner,
+            &["-c", "kitty", "--move-to-current", "--send-keys", "ctrl+l"],
+        )
+        .unwrap();
+        let keys = "CTRL, l, address:0x1".to_string();
+        assert_eq!(
+            planner.ops(),
+            [
+                Op::Move {
+                    target: "+0".into(),
+                    address: "0x1".into(),
+                    silent: false
+                },
+                Op::Focus {
+                    address: "0x1".into()
+                },
+                Op::Dispatch {
+                    dispatcher: "sendshortcut".into(),
+                    argument: keys
+                },
+            ]
+        );
+        assert!(focused.dispatches.into_inner().is_empty());
+
+        let empty = fake(None);
+        let planner = Planner::new(&empty);
+        raise_with(
+            &planner,
+            &["-c", "foot", "-e", "foot", "--after-launch-focus"],
+        )
+        .unwrap();
+        assert_eq!(
+            planner.ops(),
+            [
+                Op::Launch {
+                    command: "foot".into()
+                },
+                Op::Wait { timeout: 5000 },
+            ]
+        );
+
+        assert_eq!(
+            raise(&["-c", "kitty", "--plan", "--count"], None),
+            Err("Invalid arguments: --plan and --count are mutually exclusive".into())
+        );
+    }
+
+    #[test]
+    fn malformed_addresses_are_skipped() {
+        let torn_down = fake(Some(""));
+        torn_down.clients.borrow_mut().splice(
+            0..0,
+            [
+                client("kitty", "", false),
+                client("kitty", "garbage", false),
+            ],
+        );
+        raise_with(&torn_down, &["-c", "kitty"]).unwrap();
+        assert_eq!(
+            torn_down.dispatches.into_inner(),
+            ["focuswindow address:0x1"]
+        );
+
+        // Nothing is focused, so there is no class to cycle through
+        let torn_down = fake(Some(""));
+        torn_down
+            .clients
+            .borrow_mut()
+            .push(client("kitty", "", false));
+        raise_with(&torn_down, &["--current-class"]).unwrap();
+        assert!(torn_down.dispatches.into_inner().is_empty());
+    }
+
+    #[test]
+    fn debounce_tells_selections_apart() {
+        let dir = empty_state_dir("debounce");
+        let state = dir.to_str().unwrap();
+        let fake = fake(None);
+        fake.clients.borrow_mut()[0].title = "Inbox".into();
+        fake.clients.borrow_mut()[1].title = "Calendar".into();
+        // Without a class, only the title tells which window each bind is for
+        let by_title = |title| {
+            let debounced = ["--debounce", "60000", "--state-dir", state];
+            [&["--workspace-pattern", "*", "-t", title][..], &debounced].concat()
+        };
+        raise_with(&fake, &by_title("Inbox")).unwrap();
+        raise_with(&fake, &by_title("Calendar")).unwrap();
+        raise_with(&fake, &by_title("Calendar")).unwrap();
+        assert_eq!(
+            fake.dispatches.take(),
+            ["focuswindow address:0x1", "focuswindow address:0x2"]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn peek_puts_the_window_back_on_the_next_press() {
+        let dir = empty_state_dir("peek");
+        let state = dir.to_str().unwrap();
+
+        let fake = fake(None);
+        raise_with(&fake, &["-c", "firefox", "--peek", "--state-dir", state]).unwrap();
+        raise_with(&fake, &["-c", "firefox", "--peek", "--state-dir", state]).unwrap();
+        assert_eq!(
+            fake.dispatches.take(),
+            [
+                "focuswindow address:0x2",
+                "batch setfloating address:0x2 ; centerwindow",
+                "settiled address:0x2",
+            ]
+        );
+
+        // Floating windows go back where they were
+        fake.clients.borrow_mut()[1].floating = true;
+        raise_with(&fake, &["-c", "firefox", "--peek", "--state-dir", state]).unwrap();
+        raise_with(&fake, &["-c", "firefox", "--peek", "--state-dir", state]).unwrap();
+        assert_eq!(
+            fake.dispatches.take(),
+            [
+                "focuswindow address:0x2",
+                "centerwindow",
+                "batch movewindowpixel exact 100 50,address:0x2 ; \
+             resizewindowpixel exact 800 600,address:0x2",
+            ]
+        );
+
+        // A window that closed between peeks has nothing to put back, so the next is a peek
+        raise_with(&fake, &["-c", "firefox", "--peek", "--state-dir", state]).unwrap();
+        fake.clients.borrow_mut().remove(1);
+        raise_with(&fake, &["-c", "kitty", "--peek", "--state-dir", state]).unwrap();
+        assert_eq!(peek::load(&dir).into_keys().collect::<Vec<_>>(), ["0x1"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn peek_timeout_is_up_to_the_next_invocation() {
+        let dir = empty_state_dir("peek-timeout");
+        let state = dir.to_str().unwrap();
+        let fake = fake(None);
+        let peek = ["-c", "firefox", "--peek", "--peek-timeout", "60000"];
+        let started = Instant::now();
+        raise_with(&fake, &[&peek[..], &["--state-dir", state]].concat()).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(
+            fake.dispatches.take(),
+            [
+                "focuswindow address:0x2",
+                "batch setfloating address:0x2 ; centerwindow",
+            ]
+        );
+
+        // Not up yet, so another window leaves the peek be
+        raise_with(&fake, &["-c", "kitty", "--state-dir", state]).unwrap();
+        assert_eq!(fake.dispatches.take(), ["focuswindow address:0x1"]);
+
+        let mut peeked = peek::load(&dir);
+        peeked.get_mut("0x2").unwrap().until = Some(0);
+        peek::store(&dir, &peeked);
+        raise_with(&fake, &["-c", "mpv", "--state-dir", state]).unwrap();
+        assert_eq!(
+            fake.dispatches.take(),
+            ["settiled address:0x2", "focuswindow address:0x4"]
+        );
+        assert!(peek::load(&dir).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn urgent_first_goes_by_the_windows_noted_down() {
+        let dir = empty_state_dir("urgent");
+        let state = dir.to_str().unwrap();
+        let urgent_first = ["-c", "kitty", "--urgent-first", "--state-dir", state];
+        // Nothing noted down, so nothing is urgent
+        assert_eq!(
+            raise(&urgent_first, None),
+            Ok(vec!["focuswindow address:0x1".into()])
+        );
+        urgent::store(&dir, &["0x3".to_string()].into());
+        assert_eq!(
+            raise(&urgent_first, None),
+            Ok(vec!["focuswindow address:0x3".into()])
+        );
+        let cycle = raise(&urgent_first, Some("0x1"));
+        assert_eq!(cycle, Ok(vec!["focuswindow address:0x3".into()]));
+        // Once it is focused, cycling goes on as usual
+        let cycle = raise(&urgent_first, Some("0x3"));
+        assert_eq!(cycle, Ok(vec!["focuswindow address:0x1".into()]));
+        let plain = raise(&["-c", "kitty", "--state-dir", state], None);
+        assert_eq!(plain, Ok(vec!["focuswindow address:0x1".into()]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn watch_keeps_going_when_listing_windows_fails() {
+        let fake = Fake {
+            failures: Cell::new(1),
+            ..fake(None)
+        };
+        let args = ["-c", "kitty", "--on-urgent", "focus"];
+        let watch = Watch::from_args(&["raise watch"], &args).unwrap();
+        let urgent = Event::parse("urgent>>3").unwrap();
+        // The first event is skipped, since its window could not be looked up
+        act_on(&fake, &watch, &urgent);
+        assert!(fake.dispatches.borrow().is_empty());
+        act_on(&fake, &watch, &urgent);
+        assert_eq!(fake.dispatches.into_inner(), ["focuswindow address:0x3"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn watch_tracks_urgent_windows_until_focused_or_closed() {
+        let event = |line| Event::parse(line).unwrap();
+        let mut urgent = BTreeSet::new();
+        // Each event along with whether it changes the urgent windows
+        for (line, changed) in [
+            ("urgent>>3", true),
+            ("urgent>>4", true),
+            ("urgent>>3", false),
+            ("openwindow>>5,2,kitty,", false),
+            ("activewindowv2>>3", true),
+            ("activewindowv2>>,", false),
+            ("activewindowv2>>3", false),
+        ] {
+            assert_eq!(track_urgent(&mut urgent, &event(line)), changed, "{line}");
+        }
+        assert_eq!(urgent, BTreeSet::from(["0x4".to_string()]));
+        assert!(track_urgent(&mut urgent, &event("closewindow>>4")));
+        assert!(urgent.is_empty());
+
+        // Tracking alone acts on nothing
+        let tracking = Watch::from_args(&["raise watch"], &["--track-urgent"]).unwrap();
+        assert_eq!(
+            watched(&fake(None), &tracking, &event("urgent>>3")).unwrap(),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn watch_acts_on_events_for_the_class() {
+        let watch = |args: &[&str]| -> Watch { Watch::from_args(&["raise watch"], args).unwrap() };
+        let event = |line| Event::parse(line).unwrap();
+        let fake = fake(None);
+
+        let on_open = watch(&["-c", "kitty", "--on-open", "float"]);
+        let opened = event("openwindow>>5a1,2,kitty,vim a, b");
+        assert_eq!(
+            opened,
+            Event::OpenWindow {
+                address: "0x5a1".into(),
+                class: "kitty".into()
+            }
+        );
+        let float = ("setfloating", "address:0x5a1".to_string());
+        assert_eq!(watched(&fake, &on_open, &opened).unwrap(), Some(float));
+        let other = event("openwindow>>5a2,2,firefox,");
+        assert_eq!(watched(&fake, &on_open, &other).unwrap(), None);
+
+        // Urgent events only name the window, whose class is looked up
+        let on_urgent = watch(&["-c", "kitty", "--on-urgent", "focus"]);
+        let focus = ("focuswindow", "address:0x3".to_string());
+        assert_eq!(
+            watched(&fake, &on_urgent, &event("urgent>>3")).unwrap(),
+            Some(focus)
+        );
+        assert_eq!(
+            watched(&fake, &on_urgent, &event("urgent>>2")).unwrap(),
+            None
+        );
+
+        let on_active = watch(&["-c", "kitty", "--on-active", "tile"]);
+        let active = event("activewindow>>kitty,~");
+        assert_eq!(
+            watched(&fake, &on_active, &active).unwrap(),
+            Some(("settiled", String::new()))
+        );
+        assert_eq!(
+            Event::parse("configreloaded>>"),
+            Some(Event::ConfigReloaded)
+        );
+        assert_eq!(Event::parse("workspace>>2"), None);
+    }
+
+    #[test]
+    fn negations_turn_off_what_a_profile_turns_on() {
+        // The environment is merged in between, so it must not decide the outcome
+        std::env::set_var("RAISE_TIMINGS", "1");
+        std::env::remove_var("RAISE_PEEK");
+        let json = r#"{"profiles": {"look": {"peek": true, "timings": true}}}"#;
+        let file = miniserde::json::from_str::<ConfigFile>(json).unwrap();
+        let merged = |options: &[&str]| {
+            let options = [&["-c", "kitty", "-p", "look"], options].concat();
+            let args = Args::from_args(&["raise"], &options).unwrap();
+            let config = RaiseConfig::merge(&file, args.profile.as_deref(), args.layer()?)?;
+            Ok::<_, RaiseError>((config.peek, config.timings))
+        };
+        assert_eq!(merged(&[]).unwrap(), (true, true));
+        assert_eq!(merged(&["--no-peek"]).unwrap(), (false, true));
+        assert_eq!(merged(&["--no-timings"]).unwrap(), (true, false));
+        assert_eq!(
+            merged(&["--peek", "--no-peek"]).unwrap_err().to_string(),
+            "Invalid arguments: --peek and its negation are mutually exclusive"
+        );
+    }
+
+    #[test]
+    fn launch_lists_are_spawned_without_a_shell() {
+        let profile = |launch: &str| {
+            let term = format!(r#"{{"class": "foot", "launch": {launch}}}"#);
+            let json = format!(r#"{{"profiles": {{"term": {term}}}}}"#);
+            let file = miniserde::json::from_str::<ConfigFile>(&json).unwrap();
+            let args = Args::from_args(&["raise"], &["-p", "term"]).unwrap();
+            let config =
+                RaiseConfig::merge(&file, args.profile.as_deref(), args.layer().unwrap()).unwrap();
+            let fake = fake(None);
+            let planner = Planner::new(&fake);
+            let result = run(&planner, &args, &config, &mut Timings::default());
+            result
+                .map(|()| planner.ops())
+                .map_err(|error| error.to_string())
+        };
+        assert_eq!(
+            profile(r#"["sh", "-c", "echo 'a b' {class}"]"#),
+            Ok(vec![Op::Spawn {
+                argv: ["sh", "-c", "echo 'a b' foot"].map(String::from).to_vec(),
+                cwd: None,
+            }])
+        );
+        assert_eq!(
+            profile(r#""foot --server""#),
+            Ok(vec![Op::Launch {
+                command: "foot --server".into(),
+            }])
+        );
+        assert_eq!(
+            profile("[]"),
+            Err("Invalid arguments: the `launch` list is empty".into())
+        );
+        assert_eq!(profile(r#"["no-such-program"]"#), Err(
+            "Invalid arguments: `no-such-program`, the first of the `launch` list, is not in PATH"
+                .into()
+        ));
+    }
+
+    #[test]
+    fn placeholders_are_quoted_only_for_the_shell() {
+        let launched = |launch: &str| {
+            let json = format!(r#"{{"profiles": {{"mail": {{"launch": {launch}}}}}}}"#);
+            let file = miniserde::json::from_str::<ConfigFile>(&json).unwrap();
+            let options = ["-p", "mail", "-c", "Mail (1)", "-t", "it's; rm -rf ~ $HOME"];
+            let args = Args::from_args(&["raise"], &options).unwrap();
+            let config =
+                RaiseConfig::merge(&file, args.profile.as_deref(), args.layer().unwrap()).unwrap();
+            let fake = fake(None);
+            let planner = Planner::new(&fake);
+            run(&planner, &args, &config, &mut Timings::default()).unwrap();
+            planner.ops()
+        };
+        assert_eq!(
+            launched(r#""mail --class {class} --title {title}""#),
+            [Op::Launch {
+                command: r"mail --class 'Mail (1)' --title 'it'\''s; rm -rf ~ $HOME'".into(),
+            }]
+        );
+        assert_eq!(
+            launched(r#"["sh", "-c", "echo {title}", "{class}"]"#),
+            [Op::Spawn {
+                argv: ["sh", "-c", "echo it's; rm -rf ~ $HOME", "Mail (1)"]
+                    .map(String::from)
+                    .to_vec(),
+                cwd: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn raise_or_minimize_restores_where_the_window_was() {
+        let dir = empty_state_dir("minimize");
+        let state = dir.to_str().unwrap();
+        let fake = fake(Some("0x2"));
+        raise_with(
+            &fake,
+            &["-c", "firefox", "--raise-or-minimize", "--state-dir", state],
+        )
+        .unwrap();
+        assert_eq!(
+            fake.dispatches.take(),
+            ["movetoworkspacesilent special:minimized,address:0x2",]
+        );
+
+        // Hidden, it is restored before focusing rather than brought to the current workspace
+        fake.clients.borrow_mut()[1].workspace = raise::Workspace {
+            id: -99,
+            name: minimize::WORKSPACE.into(),
+        };
+        *fake.active.borrow_mut() = Some("0x1".into());
+        raise_with(
+            &fake,
+            &["-c", "firefox", "--raise-or-minimize", "--state-dir", state],
+        )
+        .unwrap();
+        assert_eq!(
+            fake.dispatches.take(),
+            [
+                "movetoworkspacesilent 1,address:0x2",
+                "focuswindow address:0x2",
+            ]
+        );
+        assert!(minimize::load(&dir).is_empty());
+
+        // A window taken out of hiding some other way is focused where it is
+        *fake.active.borrow_mut() = Some("0x1".into());
+        raise_with(
+            &fake,
+            &["-c", "kitty", "--raise-or-minimize", "--state-dir", state],
+        )
+        .unwrap();
+        assert_eq!(
+            fake.dispatches.take(),
+            ["movetoworkspacesilent special:minimized,address:0x1"]
+        );
+        *fake.active.borrow_mut() = Some("0x2".into());
+        raise_with(
+            &fake,
+            &["-c", "kitty", "--raise-or-minimize", "--state-dir", state],
+        )
+        .unwrap();
+        assert_eq!(fake.dispatches.take(), ["focuswindow address:0x1"]);
+        assert!(minimize::load(&dir).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
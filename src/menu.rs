@@ -0,0 +1,67 @@
+use crate::launch::find_in_path;
+use crate::{Client, RaiseError, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The dmenu-style picker used when none is given: wofi if installed, otherwise rofi
+pub fn default_command() -> &'static str {
+    if find_in_path("wofi").is_some() {
+        "wofi --dmenu"
+    } else {
+        "rofi -dmenu"
+    }
+}
+
+/// How the windows are listed in the menu, one line each, where windows that would look
+/// the same get their address added to tell them apart
+pub fn entries(clients: &[&Client]) -> Vec<String> {
+    let plain: Vec<String> = clients
+        .iter()
+        .map(|client| {
+            format!(
+                "{} — {} — {}",
+                client.class, client.title, client.workspace.name
+            )
+        })
+        .collect();
+    plain
+        .iter()
+        .zip(clients)
+        .map(
+            |(line, client)| match plain.iter().filter(|other| *other == line).count() {
+                1 => line.clone(),
+                _ => format!("{line} — {}", client.address),
+            },
+        )
+        .collect()
+}
+
+/// Let the user pick one of the clients by piping one line per window into a dmenu-style
+/// shell command and reading back the chosen line. Cancelling, which menus report by
+/// printing nothing or exiting with an error, picks nothing.
+pub fn choose<'a>(command: &str, clients: &[&'a Client]) -> Result<Option<&'a Client>> {
+    let failed = |source| RaiseError::MenuFailed {
+        command: command.to_string(),
+        source,
+    };
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(failed)?;
+    let entries = entries(clients);
+    if let Some(mut stdin) = child.stdin.take() {
+        // A menu may exit before reading everything, which is not an error on its own
+        let _ = stdin.write_all(entries.join("\n").as_bytes());
+    }
+    let output = child.wait_with_output().map_err(failed)?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let selection = String::from_utf8_lossy(&output.stdout);
+    let selection = selection.trim_end_matches(['\n', '\r']);
+    let position = entries.iter().position(|entry| entry == selection);
+    Ok(position.map(|index| clients[index]))
+}
@@ -0,0 +1,58 @@
+//! Where windows hidden with `--raise-or-minimize` were minimized from, to restore them there
+
+use crate::Workspace;
+use miniserde::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The special workspace minimized windows are hidden on
+pub const WORKSPACE: &str = "special:minimized";
+
+fn state_path(dir: &Path) -> PathBuf {
+    dir.join("raise-minimized.json")
+}
+
+/// The workspace of every minimized window, by address, from the state file in `dir`.
+/// Without a readable state file no window is minimized.
+pub fn load(dir: &Path) -> BTreeMap<String, Workspace> {
+    let contents = std::fs::read_to_string(state_path(dir)).unwrap_or_default();
+    json::from_str(&contents).unwrap_or_default()
+}
+
+/// Replace the minimized windows, ignoring failures, which only restore a window to the
+/// current workspace instead
+pub fn store(dir: &Path, minimized: &BTreeMap<String, Workspace>) {
+    let _ = std::fs::write(state_path(dir), json::to_string(minimized));
+}
+
+/// How a dispatch names the workspace: by id, or for workspaces with negative ids, which do
+/// not outlast them, by name. Special workspaces go by their `special:` name as it is,
+/// other named ones need `name:` in front.
+pub fn target(workspace: &Workspace) -> String {
+    if workspace.id > 0 {
+        workspace.id.to_string()
+    } else if workspace.name.starts_with("special:") {
+        workspace.name.clone()
+    } else {
+        format!("name:{}", workspace.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_workspaces_are_targeted_by_their_own_name() {
+        let workspace = |id, name: &str| Workspace {
+            id,
+            name: name.into(),
+        };
+        assert_eq!(target(&workspace(3, "3")), "3");
+        assert_eq!(target(&workspace(-1337, "notes")), "name:notes");
+        assert_eq!(
+            target(&workspace(-98, "special:scratch")),
+            "special:scratch"
+        );
+    }
+}
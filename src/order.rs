@@ -0,0 +1,86 @@
+use crate::Client;
+use miniserde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The order matching windows are cycled through, and counted in by `--index`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Order {
+    /// As `hyprctl clients` lists them, which is creation order
+    #[default]
+    #[serde(rename = "list")]
+    List,
+    #[serde(rename = "reverse")]
+    Reverse,
+    /// By address, which stays the same however windows are moved
+    #[serde(rename = "address")]
+    Address,
+    /// By workspace id, in list order within a workspace
+    #[serde(rename = "workspace")]
+    Workspace,
+    /// Most recently focused first
+    #[serde(rename = "mru")]
+    Mru,
+    #[serde(rename = "shuffle")]
+    Shuffle,
+}
+
+impl FromStr for Order {
+    type Err = String;
+
+    fn from_str(order: &str) -> Result<Self, Self::Err> {
+        match order {
+            "list" => Ok(Order::List),
+            "reverse" => Ok(Order::Reverse),
+            "address" => Ok(Order::Address),
+            "workspace" => Ok(Order::Workspace),
+            "mru" => Ok(Order::Mru),
+            "shuffle" => Ok(Order::Shuffle),
+            _ => Err(format!(
+                "unknown order `{order}`, expected `list`, `reverse`, `address`, `workspace`, \
+                 `mru` or `shuffle`"
+            )),
+        }
+    }
+}
+
+impl Order {
+    /// Sort clients in list order into this order, where `seed` drives `shuffle` and a
+    /// different one is picked every time without it
+    pub fn sort(self, clients: &mut [&Client], seed: Option<u64>) {
+        match self {
+            Order::List => {}
+            Order::Reverse => clients.reverse(),
+            Order::Address => clients.sort_by_key(|client| {
+                let hex = client.address.trim_start_matches("0x");
+                (u64::from_str_radix(hex, 16).ok(), client.address.clone())
+            }),
+            Order::Workspace => clients.sort_by_key(|client| client.workspace.id),
+            // Versions without a focus history keep list order
+            Order::Mru => clients.sort_by_key(|client| client.focus_history_id.unwrap_or(i64::MAX)),
+            Order::Shuffle => shuffle(clients, seed.unwrap_or_else(random_seed)),
+        }
+    }
+}
+
+fn random_seed() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_nanos() as u64 ^ u64::from(std::process::id())
+}
+
+/// Fisher-Yates with splitmix64, which is plenty for picking among a few windows
+fn shuffle<T>(items: &mut [T], mut seed: u64) {
+    let mut next = || {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    for index in (1..items.len()).rev() {
+        let other = (next() % (index as u64 + 1)) as usize;
+        items.swap(index, other);
+    }
+}
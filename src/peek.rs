@@ -0,0 +1,94 @@
+//! Remembering how windows summoned with `--peek` were laid out, to put them back after
+
+use crate::cache::now_millis;
+use crate::Client;
+use miniserde::{json, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The layout of a window before it was peeked at
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Peeked {
+    pub floating: bool,
+    /// Top left corner, as `[x, y]`, when known
+    pub at: Option<Vec<i64>>,
+    /// Width and height, as `[width, height]`, when known
+    pub size: Option<Vec<i64>>,
+    /// When the peek happened, which tells it apart from a later one of the same window
+    pub since: u64,
+    /// When `--peek-timeout` is up and the window goes back by itself, if ever
+    pub until: Option<u64>,
+}
+
+impl Peeked {
+    /// The layout of `client` now, to be put back after `timeout` milliseconds if given
+    pub fn of(client: &Client, timeout: Option<u64>) -> Self {
+        let since = now_millis();
+        Peeked {
+            floating: client.floating,
+            at: client.at.clone(),
+            size: client.size.clone(),
+            since,
+            until: timeout.map(|timeout| since + timeout),
+        }
+    }
+
+    /// Dispatches that put the window back: tiled windows are tiled again, floating ones go
+    /// back where they were
+    pub fn restore(&self, address: &str) -> Vec<(&'static str, String)> {
+        if !self.floating {
+            return vec![("settiled", format!("address:{address}"))];
+        }
+        let mut dispatches = Vec::new();
+        if let Some(&[x, y]) = self.at.as_deref() {
+            dispatches.push((
+                "movewindowpixel",
+                format!("exact {x} {y},address:{address}"),
+            ));
+        }
+        if let Some(&[width, height]) = self.size.as_deref() {
+            let argument = format!("exact {width} {height},address:{address}");
+            dispatches.push(("resizewindowpixel", argument));
+        }
+        dispatches
+    }
+}
+
+/// Dispatches that float the focused window, if it is not already, and center it
+pub fn dispatches(client: &Client) -> Vec<(&'static str, String)> {
+    let mut dispatches = Vec::new();
+    if !client.floating {
+        dispatches.push(("setfloating", format!("address:{}", client.address)));
+    }
+    dispatches.push(("centerwindow", String::new()));
+    dispatches
+}
+
+fn state_path(dir: &Path) -> PathBuf {
+    dir.join("raise-peek.json")
+}
+
+/// Windows being peeked at, by address, from the state file in `dir`. Without a readable
+/// state file there are none.
+pub fn load(dir: &Path) -> BTreeMap<String, Peeked> {
+    let contents = std::fs::read_to_string(state_path(dir)).unwrap_or_default();
+    json::from_str(&contents).unwrap_or_default()
+}
+
+/// Replace the windows being peeked at, ignoring failures, which only lose the way back
+pub fn store(dir: &Path, peeked: &BTreeMap<String, Peeked>) {
+    let _ = std::fs::write(state_path(dir), json::to_string(peeked));
+}
+
+/// Take the windows whose `--peek-timeout` is up out of the state file in `dir`. Nothing
+/// waits for the timeout, so the next invocation or `raise watch` puts them back.
+pub fn take_expired(dir: &Path) -> BTreeMap<String, Peeked> {
+    let now = now_millis();
+    let (expired, kept): (BTreeMap<_, _>, BTreeMap<_, _>) = load(dir)
+        .into_iter()
+        .partition(|(_, peeked)| peeked.until.is_some_and(|until| until <= now));
+    if !expired.is_empty() {
+        store(dir, &kept);
+    }
+    expired
+}
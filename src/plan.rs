@@ -0,0 +1,268 @@
+use crate::hyprctl::{Compositor, MOVE_DISPATCHERS};
+use crate::keys::Keys;
+use crate::launch::Spawn;
+use crate::version::Version;
+use crate::{Client, Result};
+use miniserde::json::{Array, Number, Object, Value};
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// One thing raise does to the windows or to the session, as `--plan` reports it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Focus {
+        address: String,
+    },
+    Move {
+        target: String,
+        address: String,
+        silent: bool,
+    },
+    Close {
+        address: String,
+    },
+    /// A shell command Hyprland runs
+    Launch {
+        command: String,
+    },
+    /// A program raise spawns itself
+    Spawn {
+        argv: Vec<String>,
+        cwd: Option<String>,
+    },
+    /// Waiting for a launched window, which cannot be planned past
+    Wait {
+        timeout: u64,
+    },
+    /// Keys typed with `wtype`
+    Type {
+        keys: String,
+    },
+    /// Picking between the candidates with a menu, which cannot be planned past either
+    Menu {
+        command: String,
+        candidates: u64,
+    },
+    /// Any other dispatch, as it is sent
+    Dispatch {
+        dispatcher: String,
+        argument: String,
+    },
+}
+
+impl Op {
+    /// What a dispatch does, read back from the dispatcher and its argument
+    pub fn from_dispatch(dispatcher: &str, argument: &str) -> Op {
+        let address = argument.strip_prefix("address:");
+        match (dispatcher, address) {
+            ("focuswindow", Some(address)) => Op::Focus {
+                address: address.into(),
+            },
+            ("closewindow", Some(address)) => Op::Close {
+                address: address.into(),
+            },
+            ("exec", _) => Op::Launch {
+                command: argument.into(),
+            },
+            _ => match argument.split_once(",address:") {
+                Some((target, address)) if MOVE_DISPATCHERS.contains(&dispatcher) => Op::Move {
+                    target: target.into(),
+                    address: address.into(),
+                    silent: dispatcher == "movetoworkspacesilent",
+                },
+                _ => Op::Dispatch {
+                    dispatcher: dispatcher.into(),
+                    argument: argument.into(),
+                },
+            },
+        }
+    }
+
+    /// The operation as a JSON object, named by its `op` field
+    pub fn to_json(&self) -> Value {
+        let string = |value: &str| Value::String(value.into());
+        let (op, fields) = match self {
+            Op::Focus { address } => ("focus", vec![("address", string(address))]),
+            Op::Move {
+                target,
+                address,
+                silent,
+            } => (
+                "move",
+                vec![
+                    ("target", string(target)),
+                    ("address", string(address)),
+                    ("silent", Value::Bool(*silent)),
+                ],
+            ),
+            Op::Close { address } => ("close", vec![("address", string(address))]),
+            Op::Launch { command } => ("launch", vec![("command", string(command))]),
+            Op::Spawn { argv, cwd } => {
+                let argv = argv.iter().map(|arg| string(arg)).collect::<Array>();
+                let cwd = cwd.as_deref().map_or(Value::Null, string);
+                ("spawn", vec![("argv", Value::Array(argv)), ("cwd", cwd)])
+            }
+            Op::Wait { timeout } => (
+                "wait",
+                vec![("timeout", Value::Number(Number::U64(*timeout)))],
+            ),
+            Op::Type { keys } => ("type", vec![("keys", string(keys))]),
+            Op::Menu {
+                command,
+                candidates,
+            } => (
+                "menu",
+                vec![
+                    ("command", string(command)),
+                    ("candidates", Value::Number(Number::U64(*candidates))),
+                ],
+            ),
+            Op::Dispatch {
+                dispatcher,
+                argument,
+            } => (
+                "dispatch",
+                vec![
+                    ("dispatcher", string(dispatcher)),
+                    ("argument", string(argument)),
+                ],
+            ),
+        };
+        let mut object = Object::new();
+        object.insert("op".into(), string(op));
+        for (name, value) in fields {
+            object.insert(name.into(), value);
+        }
+        Value::Object(object)
+    }
+}
+
+/// Records what raise would do instead of doing it, for `--plan`. Queries still go to the
+/// compositor underneath, where the window last focused by the plan counts as active.
+/// A launched window never appears and a menu picks nothing, as there is no telling what
+/// either would be.
+pub struct Planner<'a, C> {
+    pub inner: &'a C,
+    ops: RefCell<Vec<Op>>,
+    focused: RefCell<Option<String>>,
+}
+
+impl<'a, C: Compositor> Planner<'a, C> {
+    pub fn new(inner: &'a C) -> Self {
+        Planner {
+            inner,
+            ops: RefCell::default(),
+            focused: RefCell::default(),
+        }
+    }
+
+    /// The operations recorded so far, in order
+    pub fn ops(&self) -> Vec<Op> {
+        self.ops.borrow().clone()
+    }
+
+    fn record(&self, op: Op) {
+        if let Op::Focus { address } = &op {
+            *self.focused.borrow_mut() = Some(address.clone());
+        }
+        self.ops.borrow_mut().push(op);
+    }
+
+    fn planned_active(&self, clients: &[Client]) -> Option<Client> {
+        let focused = self.focused.borrow();
+        let address = focused.as_deref()?;
+        clients
+            .iter()
+            .find(|client| client.address == address)
+            .cloned()
+    }
+}
+
+impl<C: Compositor> Compositor for Planner<'_, C> {
+    fn check_instance(&self) -> Result<()> {
+        self.inner.check_instance()
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        self.inner.clients()
+    }
+
+    fn active_window(&self) -> Result<Client> {
+        match self.planned_active(&self.inner.clients()?) {
+            Some(client) => Ok(client),
+            None => self.inner.active_window(),
+        }
+    }
+
+    fn client_values(&self) -> Result<Vec<Value>> {
+        self.inner.client_values()
+    }
+
+    fn version(&self) -> Result<Version> {
+        self.inner.version()
+    }
+
+    fn dispatch(&self, dispatcher: &str, argument: &str) -> Result<()> {
+        self.record(Op::from_dispatch(dispatcher, argument));
+        Ok(())
+    }
+
+    fn dispatch_batch(&self, dispatches: &[(&str, String)]) -> Result<()> {
+        for (dispatcher, argument) in dispatches {
+            self.record(Op::from_dispatch(dispatcher, argument));
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<(Vec<Client>, Result<Client>)> {
+        let (clients, active) = self.inner.state()?;
+        let active = self.planned_active(&clients).map_or(active, Ok);
+        Ok((clients, active))
+    }
+
+    fn spawn(&self, program: &Spawn) -> Result<()> {
+        let cwd = program.cwd.map(|cwd| cwd.to_string_lossy().into_owned());
+        self.record(Op::Spawn {
+            argv: program.argv.to_vec(),
+            cwd,
+        });
+        Ok(())
+    }
+
+    fn type_keys(&self, keys: &Keys) -> Result<()> {
+        self.record(Op::Type {
+            keys: keys.to_string(),
+        });
+        Ok(())
+    }
+
+    // The pick is up to the user, so the plan ends in picking nothing
+    fn choose<'a>(&self, command: &str, clients: &[&'a Client]) -> Result<Option<&'a Client>> {
+        self.record(Op::Menu {
+            command: command.into(),
+            candidates: clients.len() as u64,
+        });
+        Ok(None)
+    }
+
+    fn wait_for_window(
+        &self,
+        _matches: impl Fn(&Client) -> bool,
+        _known: &[Client],
+        timeout: Duration,
+    ) -> Result<Option<Client>> {
+        self.record(Op::Wait {
+            timeout: timeout.as_millis() as u64,
+        });
+        Ok(None)
+    }
+
+    // Planned focus and closing always work out
+    fn wait_for_focus(&self, _address: &str, _timeout: Duration) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn wait_for_close(&self, _address: &str, _timeout: Duration) -> Result<bool> {
+        Ok(true)
+    }
+}
@@ -0,0 +1,78 @@
+use miniserde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// What to do when no window matches, for `--on-none`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnNone {
+    #[serde(rename = "launch")]
+    Launch,
+    #[serde(rename = "nothing")]
+    Nothing,
+}
+
+impl FromStr for OnNone {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "launch" => Ok(OnNone::Launch),
+            "nothing" => Ok(OnNone::Nothing),
+            _ => Err(format!(
+                "unknown action `{action}`, expected `launch` or `nothing`"
+            )),
+        }
+    }
+}
+
+/// What to do when one window matches, for `--on-one`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnOne {
+    /// Focus it, even when it has focus already
+    #[serde(rename = "focus")]
+    Focus,
+    /// Focus it, or go back to the previous window when it has focus
+    #[serde(rename = "toggle")]
+    Toggle,
+}
+
+impl FromStr for OnOne {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "focus" => Ok(OnOne::Focus),
+            "toggle" => Ok(OnOne::Toggle),
+            _ => Err(format!(
+                "unknown action `{action}`, expected `focus` or `toggle`"
+            )),
+        }
+    }
+}
+
+/// What to do when several windows match, for `--on-many`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnMany {
+    #[serde(rename = "cycle")]
+    Cycle,
+    /// Pick one with the menu
+    #[serde(rename = "menu")]
+    Menu,
+    /// Move them all to the current workspace
+    #[serde(rename = "gather")]
+    Gather,
+}
+
+impl FromStr for OnMany {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "cycle" => Ok(OnMany::Cycle),
+            "menu" => Ok(OnMany::Menu),
+            "gather" => Ok(OnMany::Gather),
+            _ => Err(format!(
+                "unknown action `{action}`, expected `cycle`, `menu` or `gather`"
+            )),
+        }
+    }
+}
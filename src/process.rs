@@ -0,0 +1,22 @@
+use crate::{RaiseError, Result};
+
+/// Kill a process outright with SIGKILL, for windows that ignore being asked to close
+pub fn kill(pid: i64) -> Result<()> {
+    // Zero and negative pids address process groups, which is never what is meant
+    let pid = i32::try_from(pid)
+        .ok()
+        .filter(|pid| *pid > 0)
+        .ok_or_else(|| RaiseError::InvalidArgs(format!("refusing to kill process {pid}")))?;
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+        kill(Pid::from_raw(pid), Signal::SIGKILL).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into())
+    }
+}
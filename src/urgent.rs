@@ -0,0 +1,35 @@
+//! Which windows ask for attention. Hyprland only announces it on the event socket, where
+//! `raise watch --track-urgent` notes it down for other invocations.
+
+use crate::Client;
+use miniserde::json;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+fn state_path(dir: &Path) -> PathBuf {
+    dir.join("raise-urgent.json")
+}
+
+/// Addresses of urgent windows, from the state file in `dir`. Without a readable state
+/// file none are.
+pub fn load(dir: &Path) -> BTreeSet<String> {
+    let contents = std::fs::read_to_string(state_path(dir)).unwrap_or_default();
+    let addresses = json::from_str::<Vec<String>>(&contents).unwrap_or_default();
+    addresses.into_iter().collect()
+}
+
+/// Replace the urgent windows, ignoring failures, which only lose track of them
+pub fn store(dir: &Path, urgent: &BTreeSet<String>) {
+    let addresses = urgent.iter().collect::<Vec<_>>();
+    let _ = std::fs::write(state_path(dir), json::to_string(&addresses));
+}
+
+/// Mark the clients noted down as urgent in `dir`
+pub fn mark(dir: &Path, clients: &mut [Client]) {
+    let urgent = load(dir);
+    for client in clients {
+        if urgent.contains(&client.address) {
+            client.urgent = Some(true);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{cache, hyprctl, Result};
+use miniserde::{json, Deserialize, Serialize};
+
+/// Oldest Hyprland release whose JSON output raise has been tested against
+pub const MINIMUM: (u32, u32, u32) = (0, 28, 0);
+
+/// Subset of `hyprctl version -j`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Version {
+    pub tag: String,
+    pub commit: String,
+}
+
+impl Version {
+    /// Release number from tags like `v0.28.0-72-gdeadbeef`
+    pub fn number(&self) -> Option<(u32, u32, u32)> {
+        let release = self.tag.trim_start_matches('v').split('-').next()?;
+        let mut parts = release.split('.').map(|part| part.parse::<u32>().ok());
+        Some((
+            parts.next()??,
+            parts.next()??,
+            parts.next().flatten().unwrap_or(0),
+        ))
+    }
+
+    /// Unknown tags, such as builds from source, are assumed to be recent enough
+    pub fn is_at_least(&self, minimum: (u32, u32, u32)) -> bool {
+        self.number().is_none_or(|number| number >= minimum)
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.is_at_least(MINIMUM)
+    }
+}
+
+/// The version never changes during a Hyprland session, so cache it per instance
+fn cache_path() -> Option<std::path::PathBuf> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+    Some(cache::runtime_dir().join(format!("raise-version-{signature}.json")))
+}
+
+pub fn detect() -> Result<Version> {
+    let path = cache_path();
+    if let Some(contents) = path
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+    {
+        if let Ok(version) = json::from_str::<Version>(&contents) {
+            return Ok(version);
+        }
+    }
+
+    let version = hyprctl::query::<Version>("version")?;
+    if let Some(path) = path {
+        let _ = std::fs::write(path, json::to_string(&version));
+    }
+    Ok(version)
+}